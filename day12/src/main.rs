@@ -1,38 +1,189 @@
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
 use std::fs::File;
 use std::io::prelude::*;
+use std::str::FromStr;
+use VisitPolicy::*;
 
 fn main() -> std::io::Result<()> {
-    let mut input_file = File::open(match env::args().skip(1).next() {
+    let (flags, filenames): (Vec<String>, Vec<String>) =
+        env::args().skip(1).partition(|arg| arg.starts_with("--"));
+    let mut input_file = File::open(match filenames.into_iter().next() {
         Some(filename) => filename,
         None => "input.txt".to_string(),
     })?;
     let mut input = String::new();
     input_file.read_to_string(&mut input)?;
-    part1(&input);
-    part2(&input);
+    let mut options = Options {
+        memoize: false,
+        show_paths: false,
+        through: Vec::new(),
+        limit: None,
+    };
+    for flag in flags.iter() {
+        if flag == "--memoize" {
+            options.memoize = true;
+        } else if flag == "--list-paths" {
+            options.show_paths = true;
+        } else if let Some(caves) = flag.strip_prefix("--through=") {
+            options.through = caves.split(',').map(|cave| cave.to_string()).collect();
+        } else if let Some(limit) = flag.strip_prefix("--limit=") {
+            match limit.parse() {
+                Ok(limit) => options.limit = Some(limit),
+                Err(_) => {
+                    println!("{} is not a number of paths", limit);
+                    return Ok(());
+                }
+            }
+        }
+    }
+    let caves = match parse_caves(&input) {
+        Ok(caves) => caves,
+        Err(error) => {
+            println!("invalid cave graph: {}", error);
+            return Ok(());
+        }
+    };
+    part1(&caves, &options);
+    part2(&caves, &options);
+    for flag in flags.iter() {
+        if let Some(policy) = flag.strip_prefix("--policy=") {
+            report_policy(&caves, policy, &options);
+        }
+    }
+    for flag in flags.iter() {
+        if let Some(filename) = flag.strip_prefix("--export-dot=") {
+            let edge_usage = flags
+                .iter()
+                .filter_map(|flag| flag.strip_prefix("--edge-usage="))
+                .next_back()
+                .and_then(|policy| match parse_visit_policy(&caves, policy) {
+                    Ok(visit_policy) => Some(count_edge_usage(&caves, &visit_policy)),
+                    Err(error) => {
+                        println!("{}", error);
+                        None
+                    }
+                });
+            File::create(filename)?.write_all(to_dot(&caves, edge_usage.as_ref()).as_bytes())?;
+        }
+    }
     Ok(())
 }
 
-fn part1(input: &str) {
+struct Options {
+    memoize: bool,
+    show_paths: bool,
+    through: Vec<String>,
+    limit: Option<usize>,
+}
+
+impl Options {
+    fn count_paths(&self, caves: &Caves, visit_policy: &VisitPolicy) -> Result<u64, String> {
+        if self.memoize {
+            count_paths_memoized(caves, visit_policy)
+        } else {
+            Ok(CavePaths::new(caves, visit_policy).count() as u64)
+        }
+    }
+
+    fn print_paths(&self, paths: CavePaths) {
+        if !self.show_paths {
+            return;
+        }
+        let paths = paths.filter(|path| {
+            self.through
+                .iter()
+                .all(|cave| path.contains(&cave.as_str()))
+        });
+        for path in paths.take(self.limit.unwrap_or(usize::MAX)) {
+            println!("{}", path.join(","));
+        }
+    }
+}
+
+fn part1(caves: &Caves, options: &Options) {
+    options.print_paths(CavePaths::new(caves, &SmallCavesOnce));
+    match options.count_paths(caves, &SmallCavesOnce) {
+        Ok(num_paths) => println!("Part 1: {}", num_paths),
+        Err(error) => println!("Part 1: {}", error),
+    }
+}
+
+fn part2(caves: &Caves, options: &Options) {
+    options.print_paths(CavePaths::new(caves, &OneSmallCaveTwice));
+    match options.count_paths(caves, &OneSmallCaveTwice) {
+        Ok(num_paths) => println!("Part 2: {}", num_paths),
+        Err(error) => println!("Part 2: {}", error),
+    }
+}
+
+fn report_policy(caves: &Caves, policy: &str, options: &Options) {
+    let visit_policy = match parse_visit_policy(caves, policy) {
+        Ok(visit_policy) => visit_policy,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+
+    options.print_paths(CavePaths::new(caves, &visit_policy));
+    match options.count_paths(caves, &visit_policy) {
+        Ok(num_paths) => println!("{}: {}", policy, num_paths),
+        Err(error) => println!("{}: {}", policy, error),
+    }
+}
+
+fn parse_visit_policy(caves: &Caves, policy: &str) -> Result<VisitPolicy, String> {
+    let visit_policy = policy.parse::<VisitPolicy>()?;
+    if let SmallCaveTwice(cave) = &visit_policy {
+        match caves.get(cave.as_str()) {
+            None => return Err(format!("there is no {} cave", cave)),
+            Some((_, false)) => return Err(format!("{} is a big cave", cave)),
+            Some(_) if cave == "start" => return Err("start can never be revisited".to_string()),
+            Some(_) => (),
+        }
+    }
+    Ok(visit_policy)
+}
+
+type Caves<'a> = HashMap<&'a str, (HashSet<&'a str>, bool)>;
+
+fn parse_caves(input: &str) -> Result<Caves<'_>, String> {
     lazy_static! {
-        static ref LINE_REGEX: Regex = Regex::new(r"\w+-\w+").unwrap();
-        static ref NODE_REGEX: Regex = Regex::new(r"(?P<lower>[a-z]+)|(?P<upper>[A-Z]+)").unwrap();
+        static ref NODE_REGEX: Regex =
+            Regex::new(r"^(?:(?P<lower>[a-z]+)|(?P<upper>[A-Z]+))$").unwrap();
     }
-    let mut caves = HashMap::<&str, (HashSet<&str>, bool)>::new();
+    let mut caves = Caves::new();
 
-    for line in input.lines() {
+    for line in input
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+    {
+        let (first, second) = match line.split_once('-') {
+            Some(nodes) => nodes,
+            None => return Err(format!("{} is not a connection between two caves", line)),
+        };
         let mut nodes = [("", false); 2];
-        for (i, capture) in NODE_REGEX.captures_iter(line).enumerate() {
+        for (i, name) in [first, second].into_iter().enumerate() {
+            let capture = match NODE_REGEX.captures(name) {
+                Some(capture) => capture,
+                None => return Err(format!("{} is neither a small nor a big cave", name)),
+            };
             if let Some(node) = capture.name("lower") {
                 nodes[i] = (node.as_str(), true);
             } else if let Some(node) = capture.name("upper") {
                 nodes[i] = (node.as_str(), false);
             }
         }
+        if !nodes[0].1 && !nodes[1].1 {
+            return Err(format!(
+                "big caves {} and {} are connected, so there are infinitely many paths",
+                nodes[0].0, nodes[1].0
+            ));
+        }
         caves
             .entry(nodes[0].0)
             .or_insert((HashSet::<&str>::new(), nodes[0].1))
@@ -44,117 +195,274 @@ fn part1(input: &str) {
             .0
             .insert(nodes[0].0);
     }
+    for cave in ["start", "end"] {
+        if !caves.contains_key(cave) {
+            return Err(format!("there is no {} cave", cave));
+        }
+    }
+    Ok(caves)
+}
 
-    let mut visited_small_caves = HashSet::<&str>::new();
-    let mut paths_to_explore = vec![vec!["start"]];
-    let mut current_path = Vec::<&str>::new();
-    let mut num_paths = 0;
-    while !paths_to_explore.is_empty() {
-        if let Some(alternatives) = paths_to_explore.last_mut() {
-            if let Some(cave) = alternatives.pop() {
-                if cave == "end" {
-                    num_paths += 1;
-                } else {
-                    current_path.push(cave);
-                    if caves[cave].1 {
-                        visited_small_caves.insert(&cave);
-                    }
-                    paths_to_explore.push(
-                        caves[cave]
-                            .0
-                            .iter()
-                            .filter(|other_cave| !visited_small_caves.contains(**other_cave))
-                            .map(|other_cave| *other_cave)
-                            .collect::<Vec<&str>>(),
+// Big caves can always be revisited and start never can, the policy decides for the other caves.
+enum VisitPolicy {
+    SmallCavesOnce,
+    OneSmallCaveTwice,
+    SmallCavesUpTo(usize),
+    SmallCaveTwice(String),
+}
+
+impl VisitPolicy {
+    fn may_enter(&self, cave: &str, num_visits: usize, num_revisited_caves: usize) -> bool {
+        if num_visits == 0 {
+            return true;
+        }
+        if cave == "start" {
+            return false;
+        }
+        match self {
+            SmallCavesOnce => false,
+            OneSmallCaveTwice => num_visits == 1 && num_revisited_caves == 0,
+            SmallCavesUpTo(max_visits) => num_visits < *max_visits,
+            SmallCaveTwice(twice_cave) => num_visits == 1 && cave == twice_cave,
+        }
+    }
+}
+
+impl FromStr for VisitPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "once" => Ok(SmallCavesOnce),
+            None if s == "one-twice" => Ok(OneSmallCaveTwice),
+            Some(("up-to", max_visits)) => match max_visits.parse() {
+                Ok(max_visits @ 1..) => Ok(SmallCavesUpTo(max_visits)),
+                _ => Err(format!("{} is not a positive number of visits", max_visits)),
+            },
+            Some(("twice", cave)) => Ok(SmallCaveTwice(cave.to_string())),
+            _ => Err(format!(
+                "{} is neither once, one-twice, up-to:<visits> nor twice:<cave>",
+                s
+            )),
+        }
+    }
+}
+
+fn count_edge_usage<'a>(
+    caves: &'a Caves<'a>,
+    visit_policy: &VisitPolicy,
+) -> BTreeMap<(&'a str, &'a str), usize> {
+    let mut edge_usage = BTreeMap::new();
+    for path in CavePaths::new(caves, visit_policy) {
+        for step in path.windows(2) {
+            let edge = (step[0].min(step[1]), step[0].max(step[1]));
+            *edge_usage.entry(edge).or_insert(0) += 1;
+        }
+    }
+    edge_usage
+}
+
+fn to_dot(caves: &Caves, edge_usage: Option<&BTreeMap<(&str, &str), usize>>) -> String {
+    let mut dot = String::from("graph caves {\n");
+    let mut cave_names = caves.keys().copied().collect::<Vec<_>>();
+    cave_names.sort_unstable();
+    for cave in cave_names.iter() {
+        let style = match (*cave, caves[cave].1) {
+            ("start", _) => "shape=doublecircle, style=filled, fillcolor=palegreen",
+            ("end", _) => "shape=doublecircle, style=filled, fillcolor=lightcoral",
+            (_, true) => "shape=circle",
+            (_, false) => "shape=box, style=filled, fillcolor=lightblue",
+        };
+        dot += &format!("    \"{}\" [{}];\n", cave, style);
+    }
+    let max_usage = edge_usage
+        .and_then(|edge_usage| edge_usage.values().max().copied())
+        .unwrap_or(0)
+        .max(1);
+    for cave in cave_names.iter() {
+        let mut other_caves = caves[cave]
+            .0
+            .iter()
+            .filter(|other_cave| cave < *other_cave)
+            .collect::<Vec<_>>();
+        other_caves.sort_unstable();
+        for other_cave in other_caves {
+            match edge_usage {
+                Some(edge_usage) => {
+                    let usage = edge_usage.get(&(*cave, *other_cave)).copied().unwrap_or(0);
+                    dot += &format!(
+                        "    \"{}\" -- \"{}\" [label=\"{}\", penwidth={:.2}];\n",
+                        cave,
+                        other_cave,
+                        usage,
+                        1.0 + 4.0 * usage as f64 / max_usage as f64
                     );
                 }
-            } else {
-                paths_to_explore.pop();
-                if let Some(explored_cave) = current_path.pop() {
-                    if caves[explored_cave].1 {
-                        visited_small_caves.remove(&explored_cave);
-                    }
-                }
+                None => dot += &format!("    \"{}\" -- \"{}\";\n", cave, other_cave),
             }
         }
     }
-
-    println!("Part 1: {}", num_paths);
+    dot += "}\n";
+    dot
 }
 
-fn part2(input: &str) {
-    lazy_static! {
-        static ref LINE_REGEX: Regex = Regex::new(r"\w+-\w+").unwrap();
-        static ref NODE_REGEX: Regex = Regex::new(r"(?P<lower>[a-z]+)|(?P<upper>[A-Z]+)").unwrap();
+// Counts paths without walking them, remembering how many ways lead on to end from each cave for
+// a given set of visited small caves and whether a small cave has already been revisited.
+fn count_paths_memoized(caves: &Caves, visit_policy: &VisitPolicy) -> Result<u64, String> {
+    if let SmallCavesUpTo(max_visits @ 2..) = visit_policy {
+        return Err(format!(
+            "cannot count paths visiting small caves up to {} times without walking them",
+            max_visits
+        ));
     }
-    let mut caves = HashMap::<&str, (HashSet<&str>, bool)>::new();
+    let small_caves = caves
+        .iter()
+        .filter(|(_, (_, is_small))| *is_small)
+        .map(|(cave, _)| *cave)
+        .collect::<Vec<_>>();
+    if small_caves.len() > 64 {
+        return Err(format!(
+            "cannot count paths through {} small caves without walking them",
+            small_caves.len()
+        ));
+    }
+    let small_cave_bits = small_caves
+        .iter()
+        .enumerate()
+        .map(|(i, cave)| (*cave, 1u64 << i))
+        .collect::<HashMap<_, _>>();
 
-    for line in input.lines() {
-        let mut nodes = [("", false); 2];
-        for (i, capture) in NODE_REGEX.captures_iter(line).enumerate() {
-            if let Some(node) = capture.name("lower") {
-                nodes[i] = (node.as_str(), true);
-            } else if let Some(node) = capture.name("upper") {
-                nodes[i] = (node.as_str(), false);
+    let mut num_paths_from = HashMap::new();
+    Ok(count_paths_from(
+        caves,
+        visit_policy,
+        &small_cave_bits,
+        ("start", small_cave_bits["start"], false),
+        &mut num_paths_from,
+    ))
+}
+
+fn count_paths_from<'a>(
+    caves: &Caves<'a>,
+    visit_policy: &VisitPolicy,
+    small_cave_bits: &HashMap<&str, u64>,
+    (cave, visited_small_caves, revisited): (&'a str, u64, bool),
+    num_paths_from: &mut HashMap<(&'a str, u64, bool), u64>,
+) -> u64 {
+    if cave == "end" {
+        return 1;
+    }
+    if let Some(num_paths) = num_paths_from.get(&(cave, visited_small_caves, revisited)) {
+        return *num_paths;
+    }
+    let mut num_paths = 0;
+    for other_cave in caves[cave].0.iter() {
+        let next_state = match small_cave_bits.get(other_cave) {
+            None => (*other_cave, visited_small_caves, revisited),
+            Some(bit) => {
+                let num_visits = match (visited_small_caves & bit != 0, visit_policy) {
+                    (false, _) => 0,
+                    (true, SmallCaveTwice(twice_cave)) if revisited && twice_cave == other_cave => {
+                        2
+                    }
+                    (true, _) => 1,
+                };
+                if !visit_policy.may_enter(other_cave, num_visits, revisited as usize) {
+                    continue;
+                }
+                (
+                    *other_cave,
+                    visited_small_caves | bit,
+                    revisited || num_visits > 0,
+                )
             }
+        };
+        num_paths += count_paths_from(
+            caves,
+            visit_policy,
+            small_cave_bits,
+            next_state,
+            num_paths_from,
+        );
+    }
+    num_paths_from.insert((cave, visited_small_caves, revisited), num_paths);
+    num_paths
+}
+
+// Walks the caves depth first, yielding each path from start to end as soon as it is found.
+struct CavePaths<'a, 'p> {
+    caves: &'a Caves<'a>,
+    visit_policy: &'p VisitPolicy,
+    small_cave_visits: HashMap<&'a str, usize>,
+    num_revisited_caves: usize,
+    paths_to_explore: Vec<Vec<&'a str>>,
+    current_path: Vec<&'a str>,
+}
+
+impl<'a, 'p> CavePaths<'a, 'p> {
+    fn new(caves: &'a Caves<'a>, visit_policy: &'p VisitPolicy) -> CavePaths<'a, 'p> {
+        CavePaths {
+            caves,
+            visit_policy,
+            small_cave_visits: HashMap::new(),
+            num_revisited_caves: 0,
+            paths_to_explore: vec![vec!["start"]],
+            current_path: Vec::new(),
         }
-        caves
-            .entry(nodes[0].0)
-            .or_insert((HashSet::<&str>::new(), nodes[0].1))
-            .0
-            .insert(nodes[1].0);
-        caves
-            .entry(nodes[1].0)
-            .or_insert((HashSet::<&str>::new(), nodes[1].1))
-            .0
-            .insert(nodes[0].0);
     }
+}
 
-    let mut visited_small_caves = HashSet::<&str>::new();
-    let mut paths_to_explore = vec![vec!["start"]];
-    let mut current_path = Vec::<&str>::new();
-    let mut num_paths = 0;
-    let mut visited_twice: Option<&str> = None;
-    while !paths_to_explore.is_empty() {
-        if let Some(alternatives) = paths_to_explore.last_mut() {
+impl<'a> Iterator for CavePaths<'a, '_> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let caves = self.caves;
+        while let Some(alternatives) = self.paths_to_explore.last_mut() {
             if let Some(cave) = alternatives.pop() {
                 if cave == "end" {
-                    num_paths += 1;
-                } else {
-                    current_path.push(cave);
-                    if caves[cave].1 {
-                        if visited_small_caves.contains(&cave) {
-                            visited_twice = Some(&cave);
-                        } else {
-                            visited_small_caves.insert(&cave);
-                        }
+                    let mut path = self.current_path.clone();
+                    path.push(cave);
+                    return Some(path);
+                }
+                self.current_path.push(cave);
+                if caves[cave].1 {
+                    let num_visits = self.small_cave_visits.entry(cave).or_insert(0);
+                    *num_visits += 1;
+                    if *num_visits == 2 {
+                        self.num_revisited_caves += 1;
                     }
-                    paths_to_explore.push(
-                        caves[cave]
-                            .0
-                            .iter()
-                            .filter(|other_cave| {
-                                visited_twice.is_none() && **other_cave != "start"
-                                    || !visited_small_caves.contains(**other_cave)
-                            })
-                            .map(|other_cave| *other_cave)
-                            .collect::<Vec<&str>>(),
-                    );
                 }
+                self.paths_to_explore.push(
+                    caves[cave]
+                        .0
+                        .iter()
+                        .filter(|other_cave| {
+                            !caves[**other_cave].1
+                                || self.visit_policy.may_enter(
+                                    other_cave,
+                                    self.small_cave_visits
+                                        .get(**other_cave)
+                                        .copied()
+                                        .unwrap_or(0),
+                                    self.num_revisited_caves,
+                                )
+                        })
+                        .copied()
+                        .collect::<Vec<&str>>(),
+                );
             } else {
-                paths_to_explore.pop();
-                if let Some(explored_cave) = current_path.pop() {
+                self.paths_to_explore.pop();
+                if let Some(explored_cave) = self.current_path.pop() {
                     if caves[explored_cave].1 {
-                        if visited_twice == Some(&explored_cave) {
-                            visited_twice = None;
-                        } else {
-                            visited_small_caves.remove(&explored_cave);
+                        let num_visits = self.small_cave_visits.get_mut(explored_cave).unwrap();
+                        if *num_visits == 2 {
+                            self.num_revisited_caves -= 1;
                         }
+                        *num_visits -= 1;
                     }
                 }
             }
         }
+        None
     }
-
-    println!("Part 2: {}", num_paths);
 }
@@ -1,84 +1,289 @@
+use crate::Movement::*;
+use crate::Solver::*;
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::env;
 use std::fs::File;
 use std::io::prelude::*;
+use std::time::Instant;
 
 fn main() -> std::io::Result<()> {
-    let mut input_file = File::open(match env::args().skip(1).next() {
+    let (flags, filenames): (Vec<String>, Vec<String>) =
+        env::args().skip(1).partition(|arg| arg.starts_with("--"));
+    let mut input_file = File::open(match filenames.into_iter().next() {
         Some(filename) => filename,
         None => "input.txt".to_string(),
     })?;
     let mut input = String::new();
     input_file.read_to_string(&mut input)?;
-    part1(&input);
-    part2(&input);
+    let mut options = Options {
+        solver: AStar,
+        show_path: false,
+        render: false,
+        movement: Orthogonal,
+        start: None,
+        goal: None,
+    };
+    let mut benchmark = false;
+    let mut expansion = Expansion {
+        tiles: (5, 5),
+        max_risk: 9,
+    };
+    for flag in flags.iter() {
+        if flag == "--show-path" {
+            options.show_path = true;
+        } else if flag == "--render" {
+            options.render = true;
+        } else if flag == "--bucket-queue" {
+            options.solver = BucketQueue;
+        } else if flag == "--benchmark" {
+            benchmark = true;
+        } else if flag == "--diagonal" {
+            options.movement = Diagonal { extra_cost: 0 };
+        } else if let Some(extra_cost) = flag.strip_prefix("--diagonal-cost=") {
+            match extra_cost.parse() {
+                Ok(extra_cost) => options.movement = Diagonal { extra_cost },
+                Err(_) => {
+                    println!("{} is not a diagonal step cost", extra_cost);
+                    return Ok(());
+                }
+            }
+        } else if let Some(step_costs) = flag.strip_prefix("--step-costs=") {
+            match parse_step_costs(step_costs) {
+                Some(step_costs) => options.movement = Weighted { step_costs },
+                None => {
+                    println!(
+                        "{} is not a list of step costs like N=0,E=0,SE=2",
+                        step_costs
+                    );
+                    return Ok(());
+                }
+            }
+        } else if let Some(start) = flag.strip_prefix("--start=") {
+            match parse_position(start) {
+                Some(start) => options.start = Some(start),
+                None => {
+                    println!("{} is not a position like 0,0", start);
+                    return Ok(());
+                }
+            }
+        } else if let Some(goal) = flag.strip_prefix("--goal=") {
+            match parse_position(goal) {
+                Some(goal) => options.goal = Some(goal),
+                None => {
+                    println!("{} is not a position like 0,0", goal);
+                    return Ok(());
+                }
+            }
+        } else if let Some(tiles) = flag.strip_prefix("--tiles=") {
+            match parse_tiles(tiles) {
+                Some(tiles) => expansion.tiles = tiles,
+                None => {
+                    println!("{} is not a tile count like 5x5", tiles);
+                    return Ok(());
+                }
+            }
+        } else if let Some(max_risk) = flag.strip_prefix("--max-risk=") {
+            match max_risk.parse() {
+                Ok(max_risk) if max_risk > 0 => expansion.max_risk = max_risk,
+                _ => {
+                    println!("{} is not a positive maximum risk", max_risk);
+                    return Ok(());
+                }
+            }
+        }
+    }
+    if benchmark {
+        benchmark_solvers(&input, &expansion, &options);
+        return Ok(());
+    }
+    part1(&input, &options);
+    let path = part2(&input, &expansion, &options);
+    for flag in flags.iter() {
+        if let (Some(filename), Some(path)) = (flag.strip_prefix("--export-ppm="), &path) {
+            let risk_map = parse_risk_map(&input);
+            File::create(filename)?.write_all(&render_ppm(
+                expansion.size(&risk_map),
+                path,
+                expansion.max_risk,
+                |position| expansion.risk(&risk_map, position),
+            ))?;
+        }
+    }
     Ok(())
 }
 
-fn part1(input: &str) {
-    let risk_map = input
-        .lines()
-        .map(|line| {
-            line.trim()
-                .chars()
-                .map(|c| c.to_digit(10).unwrap())
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>();
+struct Options {
+    solver: Solver,
+    show_path: bool,
+    render: bool,
+    movement: Movement,
+    start: Option<(usize, usize)>,
+    goal: Option<(usize, usize)>,
+}
+
+type Endpoints = ((usize, usize), (usize, usize));
+
+impl Options {
+    fn endpoints(&self, (y_max, x_max): (usize, usize)) -> Result<Endpoints, String> {
+        let start = self.start.unwrap_or((0, 0));
+        let goal = self.goal.unwrap_or((y_max - 1, x_max - 1));
+        for (name, (y, x)) in [("start", start), ("goal", goal)] {
+            if y >= y_max || x >= x_max {
+                return Err(format!("{} ({},{}) is outside the cave", name, x, y));
+            }
+        }
+        Ok((start, goal))
+    }
+}
+
+fn part1(input: &str, options: &Options) {
+    let risk_map = parse_risk_map(input);
     let y_max = risk_map.len();
     let x_max = risk_map[0].len();
+    let max_risk = *risk_map.iter().flatten().max().unwrap();
+    let (start, goal) = match options.endpoints((y_max, x_max)) {
+        Ok(endpoints) => endpoints,
+        Err(error) => {
+            println!("Part 1: {}", error);
+            return;
+        }
+    };
 
-    let mut path_costs = HashMap::<(usize, usize), u32>::new();
-    let mut heap = BinaryHeap::<PartialPath>::new();
+    let path = match find_shortest_path(
+        options.solver,
+        options.movement,
+        (y_max, x_max),
+        start,
+        goal,
+        max_risk,
+        |(y, x)| risk_map[y][x],
+    ) {
+        Some(path) => path,
+        None => {
+            println!("Part 1: the goal cannot be reached");
+            return;
+        }
+    };
 
-    heap.push(PartialPath {
-        position: (0, 0),
-        path_cost: (0),
-        goal: (y_max - 1, x_max - 1),
-    });
-    path_costs.insert((0, 0), 0);
-    let cost = loop {
-        let PartialPath {
-            position,
-            path_cost,
-            goal,
-        } = heap.pop().unwrap();
+    if options.show_path {
+        print_path(&path);
+    }
+    if options.render {
+        print!(
+            "{}",
+            render_ansi((y_max, x_max), &path, |(y, x)| risk_map[y][x])
+        );
+    }
+    println!("Part 1: {}", path.cost);
+}
 
-        if position == goal {
-            break path_cost;
+fn part2(input: &str, expansion: &Expansion, options: &Options) -> Option<Path> {
+    let risk_map = parse_risk_map(input);
+    let (y_max, x_max) = expansion.size(&risk_map);
+    let (start, goal) = match options.endpoints((y_max, x_max)) {
+        Ok(endpoints) => endpoints,
+        Err(error) => {
+            println!("Part 2: {}", error);
+            return None;
         }
-        if path_cost > path_costs[&position] {
-            continue;
+    };
+
+    let path = match find_shortest_path(
+        options.solver,
+        options.movement,
+        (y_max, x_max),
+        start,
+        goal,
+        expansion.max_risk,
+        |position| expansion.risk(&risk_map, position),
+    ) {
+        Some(path) => path,
+        None => {
+            println!("Part 2: the goal cannot be reached");
+            return None;
         }
-        let (y, x) = position;
-        for adjacent_y in y.saturating_sub(1)..=(y + 1).min(y_max - 1) {
-            for adjacent_x in x.saturating_sub(1)..=(x + 1).min(x_max - 1) {
-                if !(adjacent_y == y) ^ (adjacent_x == x) {
-                    continue;
-                }
-                let next_cost = path_cost + risk_map[adjacent_y][adjacent_x];
-                let next_position = (adjacent_y, adjacent_x);
-                if path_costs.contains_key(&next_position)
-                    && next_cost >= path_costs[&next_position]
-                {
-                    continue;
-                }
-                path_costs.insert(next_position, next_cost);
-                heap.push(PartialPath {
-                    position: next_position,
-                    path_cost: next_cost,
-                    goal,
-                })
-            }
+    };
+
+    if options.show_path {
+        print_path(&path);
+    }
+    if options.render {
+        print!(
+            "{}",
+            render_ansi((y_max, x_max), &path, |position| expansion
+                .risk(&risk_map, position))
+        );
+    }
+    println!("Part 2: {}", path.cost);
+    Some(path)
+}
+
+fn benchmark_solvers(input: &str, expansion: &Expansion, options: &Options) {
+    let risk_map = parse_risk_map(input);
+    let (y_max, x_max) = expansion.size(&risk_map);
+    let (start, goal) = match options.endpoints((y_max, x_max)) {
+        Ok(endpoints) => endpoints,
+        Err(error) => {
+            println!("{}", error);
+            return;
         }
     };
 
-    println!("Part 1: {}", cost);
+    for solver in [AStar, BucketQueue] {
+        let start_time = Instant::now();
+        let path = find_shortest_path(
+            solver,
+            options.movement,
+            (y_max, x_max),
+            start,
+            goal,
+            expansion.max_risk,
+            |position| expansion.risk(&risk_map, position),
+        );
+        match path {
+            Some(path) => println!("{:?}: {} in {:?}", solver, path.cost, start_time.elapsed()),
+            None => println!("{:?}: unreachable in {:?}", solver, start_time.elapsed()),
+        }
+    }
+}
+
+fn parse_position(position: &str) -> Option<(usize, usize)> {
+    let (x, y) = position.split_once(',')?;
+    Some((y.parse().ok()?, x.parse().ok()?))
 }
 
-fn part2(input: &str) {
-    let risk_map = input
+fn parse_tiles(tiles: &str) -> Option<(usize, usize)> {
+    let (width, height) = tiles.split_once('x')?;
+    match (height.parse().ok()?, width.parse().ok()?) {
+        (0, _) | (_, 0) => None,
+        tiles => Some(tiles),
+    }
+}
+
+// Costs are indexed by the step's (dy + 1, dx + 1), directions without a cost cannot be taken.
+fn parse_step_costs(step_costs: &str) -> Option<StepCosts> {
+    let mut costs = [[None; 3]; 3];
+    for step_cost in step_costs.split(',') {
+        let (direction, cost) = step_cost.split_once('=')?;
+        let (dy, dx) = match direction {
+            "N" => (0, 1),
+            "NE" => (0, 2),
+            "E" => (1, 2),
+            "SE" => (2, 2),
+            "S" => (2, 1),
+            "SW" => (2, 0),
+            "W" => (1, 0),
+            "NW" => (0, 0),
+            _ => return None,
+        };
+        costs[dy][dx] = Some(cost.parse().ok()?);
+    }
+    Some(costs)
+}
+
+fn parse_risk_map(input: &str) -> Vec<Vec<u32>> {
+    input
         .lines()
         .map(|line| {
             line.trim()
@@ -86,64 +291,308 @@ fn part2(input: &str) {
                 .map(|c| c.to_digit(10).unwrap())
                 .collect::<Vec<_>>()
         })
+        .collect::<Vec<_>>()
+}
+
+// Tiles are counted as (down, across), each tile's risks being one higher than the tile above or
+// to its left, wrapping back around to 1 after max_risk.
+struct Expansion {
+    tiles: (usize, usize),
+    max_risk: u32,
+}
+
+impl Expansion {
+    fn size(&self, risk_map: &[Vec<u32>]) -> (usize, usize) {
+        (
+            self.tiles.0 * risk_map.len(),
+            self.tiles.1 * risk_map[0].len(),
+        )
+    }
+
+    fn risk(&self, risk_map: &[Vec<u32>], (y, x): (usize, usize)) -> u32 {
+        let original_y_max = risk_map.len();
+        let original_x_max = risk_map[0].len();
+        (risk_map[y % original_y_max][x % original_x_max]
+            + (y / original_y_max) as u32
+            + (x / original_x_max) as u32
+            - 1)
+            % self.max_risk
+            + 1
+    }
+}
+
+fn print_path(path: &Path) {
+    let positions = path
+        .positions
+        .iter()
+        .map(|(y, x)| format!("({},{})", x, y))
         .collect::<Vec<_>>();
-    let original_y_max = risk_map.len();
-    let original_x_max = risk_map[0].len();
-    let y_max = 5 * original_y_max;
-    let x_max = 5 * original_x_max;
+    println!("{}", positions.join(" -> "));
+}
+
+fn render_ansi<F>((y_max, x_max): (usize, usize), path: &Path, risk: F) -> String
+where
+    F: Fn((usize, usize)) -> u32,
+{
+    let on_path = path.positions.iter().collect::<HashSet<_>>();
+    let mut output = String::new();
+    for y in 0..y_max {
+        for x in 0..x_max {
+            if on_path.contains(&(y, x)) {
+                output += &format!("\x1b[1;31m{}\x1b[0m", risk((y, x)));
+            } else {
+                output += &format!("\x1b[2m{}\x1b[0m", risk((y, x)));
+            }
+        }
+        output.push('\n');
+    }
+    output
+}
 
+// Binary PPM with brightness scaled by risk, the path drawn in red.
+fn render_ppm<F>((y_max, x_max): (usize, usize), path: &Path, max_risk: u32, risk: F) -> Vec<u8>
+where
+    F: Fn((usize, usize)) -> u32,
+{
+    let on_path = path.positions.iter().collect::<HashSet<_>>();
+    let mut output = format!("P6\n{} {}\n255\n", x_max, y_max).into_bytes();
+    for y in 0..y_max {
+        for x in 0..x_max {
+            let brightness = (risk((y, x)) * 255 / max_risk) as u8;
+            if on_path.contains(&(y, x)) {
+                output.extend([255, brightness / 2, brightness / 2]);
+            } else {
+                output.extend([brightness, brightness, brightness]);
+            }
+        }
+    }
+    output
+}
+
+struct Path {
+    cost: u32,
+    positions: Vec<(usize, usize)>,
+}
+
+type StepCosts = [[Option<u32>; 3]; 3];
+
+// Diagonal steps cost extra_cost, and weighted steps their direction's cost, on top of the risk
+// of the position entered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Movement {
+    Orthogonal,
+    Diagonal { extra_cost: u32 },
+    Weighted { step_costs: StepCosts },
+}
+
+impl Movement {
+    fn neighbours(
+        self,
+        (y, x): (usize, usize),
+        (y_max, x_max): (usize, usize),
+    ) -> impl Iterator<Item = ((usize, usize), u32)> {
+        (y.saturating_sub(1)..=(y + 1).min(y_max - 1))
+            .flat_map(move |adjacent_y| {
+                (x.saturating_sub(1)..=(x + 1).min(x_max - 1))
+                    .map(move |adjacent_x| (adjacent_y, adjacent_x))
+            })
+            .filter_map(move |(adjacent_y, adjacent_x)| {
+                let step_cost = self.step_cost((adjacent_y + 1 - y, adjacent_x + 1 - x))?;
+                Some(((adjacent_y, adjacent_x), step_cost))
+            })
+    }
+
+    fn step_cost(self, (dy, dx): (usize, usize)) -> Option<u32> {
+        match self {
+            Weighted { step_costs } => step_costs[dy][dx],
+            _ if (dy, dx) == (1, 1) => None,
+            _ if dy == 1 || dx == 1 => Some(0),
+            Orthogonal => None,
+            Diagonal { extra_cost } => Some(extra_cost),
+        }
+    }
+
+    fn step_costs(self) -> impl Iterator<Item = u32> {
+        (0..3)
+            .flat_map(|dy| (0..3).map(move |dx| (dy, dx)))
+            .filter_map(move |offset| self.step_cost(offset))
+    }
+
+    fn max_step_cost(self) -> u32 {
+        self.step_costs().max().unwrap_or(0)
+    }
+
+    // Every position costs at least 1 to enter, so a diagonal step is never cheaper than
+    // min(1 + extra_cost, 2) towards covering one row and one column. Weighted steps can cover
+    // at most one row and one column each, for at least 1 plus the cheapest step cost.
+    fn heuristic(self, start: &(usize, usize), goal: &(usize, usize)) -> u32 {
+        let dy = start.0.abs_diff(goal.0) as u32;
+        let dx = start.1.abs_diff(goal.1) as u32;
+        match self {
+            Orthogonal => dy + dx,
+            Diagonal { extra_cost } => {
+                dy.max(dx) - dy.min(dx) + dy.min(dx) * (1 + extra_cost).min(2)
+            }
+            Weighted { step_costs } => {
+                let diagonal = [(0, 0), (0, 2), (2, 0), (2, 2)]
+                    .iter()
+                    .any(|&(row, column)| step_costs[row][column].is_some());
+                let steps = if diagonal { dy.max(dx) } else { dy + dx };
+                steps * (1 + self.step_costs().min().unwrap_or(0))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Solver {
+    AStar,
+    BucketQueue,
+}
+
+// The cost function gives the cost of entering a position, the start position's cost is never paid.
+fn find_shortest_path<F>(
+    solver: Solver,
+    movement: Movement,
+    size: (usize, usize),
+    start: (usize, usize),
+    goal: (usize, usize),
+    max_cost: u32,
+    cost: F,
+) -> Option<Path>
+where
+    F: Fn((usize, usize)) -> u32,
+{
+    match solver {
+        AStar => find_shortest_path_with_heap(movement, size, start, goal, cost),
+        BucketQueue => find_shortest_path_with_buckets(
+            movement,
+            size,
+            start,
+            goal,
+            max_cost + movement.max_step_cost(),
+            cost,
+        ),
+    }
+}
+
+fn find_shortest_path_with_heap<F>(
+    movement: Movement,
+    size: (usize, usize),
+    start: (usize, usize),
+    goal: (usize, usize),
+    cost: F,
+) -> Option<Path>
+where
+    F: Fn((usize, usize)) -> u32,
+{
     let mut path_costs = HashMap::<(usize, usize), u32>::new();
+    let mut predecessors = HashMap::<(usize, usize), (usize, usize)>::new();
     let mut heap = BinaryHeap::<PartialPath>::new();
 
     heap.push(PartialPath {
-        position: (0, 0),
-        path_cost: (0),
-        goal: (y_max - 1, x_max - 1),
+        position: start,
+        path_cost: 0,
+        goal,
+        movement,
     });
-    path_costs.insert((0, 0), 0);
-    let cost = loop {
-        let PartialPath {
-            position,
-            path_cost,
-            goal,
-        } = heap.pop().unwrap();
-
+    path_costs.insert(start, 0);
+    while let Some(PartialPath {
+        position,
+        path_cost,
+        goal,
+        movement,
+    }) = heap.pop()
+    {
         if position == goal {
-            break path_cost;
+            let mut positions = vec![goal];
+            while let Some(predecessor) = predecessors.get(positions.last().unwrap()) {
+                positions.push(*predecessor);
+            }
+            positions.reverse();
+            return Some(Path {
+                cost: path_cost,
+                positions,
+            });
         }
         if path_cost > path_costs[&position] {
             continue;
         }
-        let (y, x) = position;
-        for adjacent_y in y.saturating_sub(1)..=(y + 1).min(y_max - 1) {
-            for adjacent_x in x.saturating_sub(1)..=(x + 1).min(x_max - 1) {
-                if !(adjacent_y == y) ^ (adjacent_x == x) {
-                    continue;
+        for (next_position, step_cost) in movement.neighbours(position, size) {
+            let next_cost = path_cost + cost(next_position) + step_cost;
+            if path_costs.contains_key(&next_position) && next_cost >= path_costs[&next_position] {
+                continue;
+            }
+            path_costs.insert(next_position, next_cost);
+            predecessors.insert(next_position, position);
+            heap.push(PartialPath {
+                position: next_position,
+                path_cost: next_cost,
+                goal,
+                movement,
+            })
+        }
+    }
+    None
+}
+
+// Dial's algorithm: with entry costs of at most max_cost, every queued path costs between the
+// current cost and max_cost more, so max_cost + 1 buckets indexed by cost modulo their number
+// suffice.
+fn find_shortest_path_with_buckets<F>(
+    movement: Movement,
+    (y_max, x_max): (usize, usize),
+    start: (usize, usize),
+    goal: (usize, usize),
+    max_cost: u32,
+    cost: F,
+) -> Option<Path>
+where
+    F: Fn((usize, usize)) -> u32,
+{
+    let index = |(y, x): (usize, usize)| y * x_max + x;
+    let mut path_costs = vec![u32::MAX; y_max * x_max];
+    let mut predecessors = vec![usize::MAX; y_max * x_max];
+    let num_buckets = max_cost as usize + 1;
+    let mut buckets = vec![Vec::<(usize, usize)>::new(); num_buckets];
+    let mut num_queued = 1;
+
+    path_costs[index(start)] = 0;
+    buckets[0].push(start);
+    let mut path_cost = 0;
+    while num_queued > 0 {
+        let bucket = path_cost as usize % num_buckets;
+        while let Some(position) = buckets[bucket].pop() {
+            num_queued -= 1;
+            if path_cost > path_costs[index(position)] {
+                continue;
+            }
+            if position == goal {
+                let mut positions = vec![goal];
+                while predecessors[index(*positions.last().unwrap())] != usize::MAX {
+                    let predecessor = predecessors[index(*positions.last().unwrap())];
+                    positions.push((predecessor / x_max, predecessor % x_max));
                 }
-                let next_cost = path_cost
-                    + (risk_map[adjacent_y % original_y_max][adjacent_x % original_x_max]
-                        + (adjacent_y / original_y_max) as u32
-                        + (adjacent_x / original_x_max) as u32
-                        - 1)
-                        % 9
-                    + 1;
-                let next_position = (adjacent_y, adjacent_x);
-                if path_costs.contains_key(&next_position)
-                    && next_cost >= path_costs[&next_position]
-                {
+                positions.reverse();
+                return Some(Path {
+                    cost: path_cost,
+                    positions,
+                });
+            }
+            for (next_position, step_cost) in movement.neighbours(position, (y_max, x_max)) {
+                let next_cost = path_cost + cost(next_position) + step_cost;
+                if next_cost >= path_costs[index(next_position)] {
                     continue;
                 }
-                path_costs.insert(next_position, next_cost);
-                heap.push(PartialPath {
-                    position: next_position,
-                    path_cost: next_cost,
-                    goal,
-                })
+                path_costs[index(next_position)] = next_cost;
+                predecessors[index(next_position)] = index(position);
+                buckets[next_cost as usize % num_buckets].push(next_position);
+                num_queued += 1;
             }
         }
-    };
-
-    println!("Part 2: {}", cost);
+        path_cost += 1;
+    }
+    None
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
@@ -151,13 +600,14 @@ struct PartialPath {
     position: (usize, usize),
     path_cost: u32,
     goal: (usize, usize),
+    movement: Movement,
 }
 
 impl Ord for PartialPath {
     fn cmp(&self, other: &Self) -> Ordering {
         u32::cmp(
-            &(other.path_cost + heuristic(&other.position, &other.goal)),
-            &(&self.path_cost + heuristic(&self.position, &self.goal)),
+            &(other.path_cost + other.movement.heuristic(&other.position, &other.goal)),
+            &(self.path_cost + self.movement.heuristic(&self.position, &self.goal)),
         )
     }
 }
@@ -167,17 +617,3 @@ impl PartialOrd for PartialPath {
         Some(self.cmp(other))
     }
 }
-
-fn heuristic(start: &(usize, usize), goal: &(usize, usize)) -> u32 {
-    let dy = if start.1 > goal.1 {
-        start.1 - goal.1
-    } else {
-        goal.1 - start.1
-    };
-    let dx = if start.0 > goal.0 {
-        start.0 - goal.0
-    } else {
-        goal.0 - start.0
-    };
-    (dy + dx) as u32
-}
@@ -1,83 +1,148 @@
+use crate::MatchingStrategy::*;
+use geometry::{Coordinate3D, Cuboid, RotationMatrix3D, ROTATIONS};
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
 use std::fs::File;
 use std::hash::Hash;
 use std::io::prelude::*;
-use std::mem::swap;
-use std::ops::{Add, Mul, Sub};
+use std::io::{self, ErrorKind};
+use std::str::FromStr;
 
 fn main() -> std::io::Result<()> {
-    let mut input_file = File::open(match env::args().skip(1).next() {
+    let (flags, filenames): (Vec<String>, Vec<String>) =
+        env::args().skip(1).partition(|arg| arg.starts_with("--"));
+    let mut input_file = File::open(match filenames.into_iter().next() {
         Some(filename) => filename,
         None => "input.txt".to_string(),
     })?;
     let mut input = String::new();
     input_file.read_to_string(&mut input)?;
-    part1(&input);
+    let strategy = if flags.iter().any(|flag| flag == "--brute-force") {
+        BruteForce
+    } else {
+        Fingerprint
+    };
+    let alignment = part1(&input, strategy);
     part2(&input);
+    for flag in flags.iter() {
+        if flag == "--verify" {
+            verify_matching(&input);
+        } else if flag == "--overlaps" {
+            for ((scanner, other), overlap_count) in alignment.overlaps.iter() {
+                print_overlap(*scanner, *other, *overlap_count);
+            }
+        } else if let Some(filename) = flag.strip_prefix("--export-ply=") {
+            File::create(filename)?.write_all(alignment.point_cloud().to_ply().as_bytes())?;
+        } else if let Some(filename) = flag.strip_prefix("--export-xyz=") {
+            File::create(filename)?.write_all(alignment.point_cloud().to_xyz().as_bytes())?;
+        } else if let Some(filename) = flag.strip_prefix("--compare=") {
+            let mut cloud_input = String::new();
+            File::open(filename)?.read_to_string(&mut cloud_input)?;
+            let cloud = PointCloud::from_str(&cloud_input)
+                .map_err(|error| io::Error::new(ErrorKind::InvalidData, error))?;
+            compare_point_clouds(&alignment.point_cloud(), &cloud);
+        }
+    }
     Ok(())
 }
 
-fn part1(input: &str) {
-    lazy_static! {
-        static ref EMPTY_LINE_REGEX: Regex = Regex::new(r"\r?\n\r?\n").unwrap();
-        static ref SCANNER_REGEX: Regex = Regex::new(r"--- scanner (?P<scanner>\d+) ---").unwrap();
-        static ref BEACON_REGEX: Regex = Regex::new(r"(-?\d+),(-?\d+),(-?\d+)").unwrap();
-    }
-
-    let mut absolute_positions = HashSet::<Coordinate3D>::new();
-
-    let mut unresolved_set = HashMap::<u32, Scanner>::new();
-    let mut open_set = Vec::<Scanner>::new();
-    let mut closed_set = Vec::<Scanner>::new();
-
-    let mut rotation_sets = Vec::new();
-    let mut rotation_matrices = Vec::<RotationMatrix3D>::new();
-    let mut i = 0;
-    let signs = [1, -1];
-    for x_sign in signs {
-        for x in 0..3 {
-            let mut x_vector = [0, 0, 0];
-            x_vector[x] = x_sign;
-            for y_sign in signs {
-                for y in (x + 1..=x + 2).map(|j| j % 3) {
-                    let mut y_vector = [0, 0, 0];
-                    y_vector[y] = y_sign;
-
-                    let z = 3 - x - y;
-                    let z_sign = signs[i % 2] * x_sign * y_sign;
-                    let mut z_vector = [0, 0, 0];
-                    z_vector[z] = z_sign;
-
-                    rotation_matrices.push(RotationMatrix3D([x_vector, y_vector, z_vector]));
-
-                    i += 1;
-                }
+fn part1(input: &str, strategy: MatchingStrategy) -> Alignment {
+    let alignment = align_scanners(parse_scanners(input), strategy);
+
+    let unresolved = alignment.unresolved();
+    if !unresolved.is_empty() {
+        println!(
+            "Warning: scanners {:?} could not be resolved relative to scanner 0",
+            unresolved
+        );
+        for component in alignment.components.iter().skip(1) {
+            if component.len() < 2 {
+                continue;
+            }
+            println!(
+                "Warning: scanners {:?} align with each other but not with scanner 0",
+                component
+                    .iter()
+                    .map(|scanner| scanner.id)
+                    .collect::<Vec<_>>()
+            );
+        }
+        for ((scanner, other), overlap_count) in alignment.overlaps.iter() {
+            if unresolved.contains(scanner) || unresolved.contains(other) {
+                print_overlap(*scanner, *other, *overlap_count);
             }
         }
     }
-    for _ in 0..24 {
-        rotation_sets.push(HashSet::<Coordinate3D>::new())
+
+    println!("Part 1: {}", alignment.absolute_positions().len());
+
+    let manhattan_distance: i32 = alignment.components[0]
+        .iter()
+        .flat_map(|scanner| {
+            alignment.components[0]
+                .iter()
+                .map(|other| scanner.position.manhattan_distance(&other.position))
+        })
+        .max()
+        .unwrap();
+    println!("Part 2: {}", manhattan_distance);
+
+    alignment
+}
+
+fn part2(_input: &str) {}
+
+fn print_overlap(scanner: u32, other: u32, overlap_count: usize) {
+    println!(
+        "Scanners {} and {} share {} of the {} inner vectors needed to match",
+        scanner, other, overlap_count, MIN_SHARED_INNER_VECTORS
+    );
+}
+
+fn verify_matching(input: &str) {
+    let fingerprint_alignment = align_scanners(parse_scanners(input), Fingerprint);
+    let brute_force_alignment = align_scanners(parse_scanners(input), BruteForce);
+
+    let scanner_positions = |alignment: &Alignment| {
+        alignment
+            .components
+            .iter()
+            .map(|component| {
+                component
+                    .iter()
+                    .map(|scanner| (scanner.id, scanner.position))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    };
+    if scanner_positions(&fingerprint_alignment) == scanner_positions(&brute_force_alignment)
+        && fingerprint_alignment.absolute_positions() == brute_force_alignment.absolute_positions()
+    {
+        println!("Verification: fingerprint and brute-force matching agree");
+    } else {
+        println!("Verification: fingerprint and brute-force matching disagree");
+        println!(
+            "Fingerprint: {:?}",
+            scanner_positions(&fingerprint_alignment)
+        );
+        println!(
+            "Brute force: {:?}",
+            scanner_positions(&brute_force_alignment)
+        );
     }
+}
 
-    let mut scanner_data_blocks = EMPTY_LINE_REGEX.split(input);
-    let mut beacons = HashSet::new();
-
-    for beacon_capture in BEACON_REGEX.captures_iter(scanner_data_blocks.next().unwrap()) {
-        let beacon_position = Coordinate3D([
-            beacon_capture[1].parse().unwrap(),
-            beacon_capture[2].parse().unwrap(),
-            beacon_capture[3].parse().unwrap(),
-        ]);
-        beacons.insert(beacon_position);
-        absolute_positions.insert(beacon_position);
+fn parse_scanners(input: &str) -> Vec<Scanner> {
+    lazy_static! {
+        static ref EMPTY_LINE_REGEX: Regex = Regex::new(r"\r?\n\r?\n").unwrap();
+        static ref SCANNER_REGEX: Regex = Regex::new(r"--- scanner (?P<scanner>\d+) ---").unwrap();
+        static ref BEACON_REGEX: Regex = Regex::new(r"(-?\d+),(-?\d+),(-?\d+)").unwrap();
     }
-    let scanner = Scanner::new(0, Coordinate3D([0, 0, 0]), beacons);
-    open_set.push(scanner);
 
-    for scanner_data in scanner_data_blocks {
+    let mut scanners = Vec::new();
+    for scanner_data in EMPTY_LINE_REGEX.split(input.trim()) {
         let scanner_id: u32 = SCANNER_REGEX.captures(scanner_data).unwrap()["scanner"]
             .parse()
             .unwrap();
@@ -90,191 +155,320 @@ fn part1(input: &str) {
             ]);
             beacons.insert(relative_position);
         }
-        let scanner = Scanner::new(scanner_id, Coordinate3D([0, 0, 0]), beacons);
-        unresolved_set.insert(scanner_id, scanner);
+        scanners.push(Scanner::new(scanner_id, Coordinate3D::ORIGIN, beacons));
     }
+    scanners
+}
 
-    while let Some(scanner) = open_set.pop() {
-        let mut resolved_set = HashSet::new();
-        for (
-            id,
-            Scanner {
-                beacons: relative_beacons,
-                inner_vectors,
-                ..
-            },
-        ) in unresolved_set.iter()
-        {
-            if scanner.inner_vectors.intersection(inner_vectors).count() < 66 {
-                // 12 * (12 - 1) / 2
-                continue;
+fn compare_point_clouds(reconstructed: &PointCloud, imported: &PointCloud) {
+    for (name, reconstructed_points, imported_points) in [
+        ("beacons", &reconstructed.beacons, &imported.beacons),
+        ("scanners", &reconstructed.scanners, &imported.scanners),
+    ] {
+        println!(
+            "Comparison: {} {} in common, {} only reconstructed, {} only imported",
+            reconstructed_points.intersection(imported_points).count(),
+            name,
+            reconstructed_points.difference(imported_points).count(),
+            imported_points.difference(reconstructed_points).count()
+        );
+    }
+}
+
+// Twelve shared beacons give 12 * (12 - 1) / 2 shared inner vectors.
+const MIN_SHARED_INNER_VECTORS: usize = 66;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MatchingStrategy {
+    Fingerprint,
+    BruteForce,
+}
+
+// Overlaps count the inner vectors shared by each pair of scanners, which doesn't depend on
+// whether either of them could be aligned.
+#[derive(Debug)]
+struct Alignment {
+    components: Vec<Vec<Scanner>>,
+    overlaps: BTreeMap<(u32, u32), usize>,
+}
+
+impl Alignment {
+    fn absolute_positions(&self) -> HashSet<Coordinate3D> {
+        self.components[0]
+            .iter()
+            .flat_map(|scanner| scanner.beacons.iter().copied())
+            .collect()
+    }
+
+    fn point_cloud(&self) -> PointCloud {
+        PointCloud {
+            beacons: self.absolute_positions(),
+            scanners: self.components[0]
+                .iter()
+                .map(|scanner| scanner.position)
+                .collect(),
+        }
+    }
+
+    fn unresolved(&self) -> Vec<u32> {
+        let mut unresolved = self
+            .components
+            .iter()
+            .skip(1)
+            .flatten()
+            .map(|scanner| scanner.id)
+            .collect::<Vec<_>>();
+        unresolved.sort_unstable();
+        unresolved
+    }
+}
+
+fn align_scanners(scanners: Vec<Scanner>, strategy: MatchingStrategy) -> Alignment {
+    let mut overlaps = BTreeMap::new();
+    for (i, scanner) in scanners.iter().enumerate() {
+        for other in scanners.iter().skip(i + 1) {
+            let overlap_count = scanner.shared_inner_vectors(other);
+            if overlap_count > 0 {
+                overlaps.insert((scanner.id, other.id), overlap_count);
             }
+        }
+    }
 
-            'rotations: for rotated_coordinates in rotation_matrices.iter().map(|rotation| {
-                relative_beacons
-                    .iter()
-                    .map(|coordinates| *rotation * *coordinates)
-                    .collect::<HashSet<Coordinate3D>>()
-            }) {
-                for rotated_coordinate in rotated_coordinates.iter() {
-                    'next_comparison: for absolute_coordinate in scanner.beacons.iter() {
-                        let offset = absolute_coordinate - rotated_coordinate;
-                        let offset_set = &rotated_coordinates + offset;
-
-                        //let overlap_count = scanner.beacons.intersection(&offset_set).count();
-                        let mut overlap_count = 0;
-                        for absolute_coordinate in scanner.beacons.iter() {
-                            if offset_set.contains(absolute_coordinate) {
-                                overlap_count += 1;
-                            } else {
-                                let [ox, oy, oz] = offset.0;
-                                let [ax, ay, az] = absolute_coordinate.0;
-                                if (ox - 1000..=ox + 1000).contains(&ax)
-                                    && (oy - 1000..=oy + 1000).contains(&ay)
-                                    && (oz - 1000..=oz + 1000).contains(&az)
-                                {
-                                    continue 'next_comparison;
-                                }
-                            }
-                        }
-
-                        if overlap_count >= 12 {
-                            resolved_set.insert(Scanner {
-                                id: *id,
-                                position: offset,
-                                beacons: offset_set,
-                                inner_vectors: inner_vectors.clone(),
-                            });
-                            break 'rotations;
-                        }
-                    }
+    let mut unresolved_set = scanners
+        .into_iter()
+        .map(|scanner| (scanner.id, scanner))
+        .collect::<BTreeMap<u32, Scanner>>();
+    let mut components = Vec::new();
+
+    while let Some((_, root)) = unresolved_set.pop_first() {
+        let mut open_set = vec![root];
+        let mut closed_set = Vec::<Scanner>::new();
+
+        while let Some(scanner) = open_set.pop() {
+            let mut resolved_set = HashSet::new();
+            for candidate in unresolved_set.values() {
+                let resolved_scanner = match strategy {
+                    Fingerprint => align_by_fingerprint(&scanner, candidate),
+                    BruteForce => align_by_brute_force(&scanner, candidate),
+                };
+                if let Some(resolved_scanner) = resolved_scanner {
+                    resolved_set.insert(resolved_scanner);
                 }
             }
-        }
-        for resolved_scanner in resolved_set {
-            for coordinate in resolved_scanner.beacons.iter() {
-                absolute_positions.insert(*coordinate);
+            for resolved_scanner in resolved_set {
+                unresolved_set.remove(&resolved_scanner.id);
+                open_set.push(resolved_scanner);
             }
-            unresolved_set.remove(&resolved_scanner.id);
-            open_set.push(resolved_scanner);
+            closed_set.push(scanner);
         }
-        closed_set.push(scanner);
+        closed_set.sort_unstable_by_key(|scanner| scanner.id);
+        components.push(closed_set);
     }
 
-    println!("Part 1: {}", absolute_positions.len());
-
-    let manhattan_distance: i32 = closed_set
-        .iter()
-        .flat_map(|scanner| {
-            closed_set.iter().map(|other| {
-                (&scanner.position - other.position)
-                    .0
-                    .into_iter()
-                    .fold(0, |a, b| a + b.abs())
-            })
-        })
-        .max()
-        .unwrap();
-    println!("Part 2: {}", manhattan_distance);
+    Alignment {
+        components,
+        overlaps,
+    }
 }
 
-fn part2(_input: &str) {}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-struct Coordinate3D([i32; 3]);
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-struct RotationMatrix3D([[i32; 3]; 3]);
-
-impl Mul<Coordinate3D> for RotationMatrix3D {
-    type Output = Coordinate3D;
+fn align_by_brute_force(scanner: &Scanner, candidate: &Scanner) -> Option<Scanner> {
+    if scanner.shared_inner_vectors(candidate) < MIN_SHARED_INNER_VECTORS {
+        return None;
+    }
 
-    fn mul(self, rhs: Coordinate3D) -> Self::Output {
-        let mut output = Coordinate3D([0, 0, 0]);
-        for i in 0..3 {
-            for j in 0..3 {
-                output.0[i] += self.0[j][i] * rhs.0[j];
+    for rotation in ROTATIONS.iter() {
+        let rotated_coordinates = candidate
+            .beacons
+            .iter()
+            .map(|coordinates| *rotation * coordinates)
+            .collect::<HashSet<Coordinate3D>>();
+        for rotated_coordinate in rotated_coordinates.iter() {
+            for absolute_coordinate in scanner.beacons.iter() {
+                let offset = absolute_coordinate - rotated_coordinate;
+                let offset_set = rotated_coordinates
+                    .iter()
+                    .map(|coordinate| coordinate + offset)
+                    .collect();
+                if overlaps(scanner, &offset_set, offset) {
+                    return Some(candidate.transformed(*rotation, offset));
+                }
             }
         }
-        output
     }
+    None
 }
 
-impl Coordinate3D {
-    fn abs(&self) -> Self {
-        Self([self.0[0].abs(), self.0[1].abs(), self.0[2].abs()])
+fn align_by_fingerprint(scanner: &Scanner, candidate: &Scanner) -> Option<Scanner> {
+    if scanner.shared_inner_vectors(candidate) < MIN_SHARED_INNER_VECTORS {
+        return None;
     }
 
-    fn normalise(&mut self) {
-        let [mut x, mut y, mut z] = self.0;
-        if x > y {
-            swap(&mut x, &mut y)
-        }
-        if x > z {
-            swap(&mut x, &mut z)
+    let mut votes = HashMap::<(RotationMatrix3D, Coordinate3D), usize>::new();
+    for (inner_vector, relative_pairs) in candidate.inner_vectors.iter() {
+        let absolute_pairs = match scanner.inner_vectors.get(inner_vector) {
+            Some(absolute_pairs) => absolute_pairs,
+            None => continue,
+        };
+        for (absolute_start, absolute_end) in absolute_pairs.iter() {
+            let absolute_vector = absolute_end - absolute_start;
+            for (relative_start, relative_end) in relative_pairs.iter() {
+                let relative_vector = relative_end - relative_start;
+                for rotation in ROTATIONS.iter() {
+                    let rotated_vector = *rotation * relative_vector;
+                    let relative_anchor = if rotated_vector == absolute_vector {
+                        relative_start
+                    } else if rotated_vector == -absolute_vector {
+                        relative_end
+                    } else {
+                        continue;
+                    };
+                    let offset = absolute_start - *rotation * relative_anchor;
+                    *votes.entry((*rotation, offset)).or_insert(0) += 1;
+                }
+            }
         }
-        if y > z {
-            swap(&mut y, &mut z)
+    }
+
+    let mut proposals = votes
+        .into_iter()
+        .filter(|(_, num_votes)| *num_votes >= 12)
+        .collect::<Vec<_>>();
+    proposals.sort_unstable_by(|(a, a_votes), (b, b_votes)| b_votes.cmp(a_votes).then(a.cmp(b)));
+    for ((rotation, offset), _) in proposals {
+        let offset_set = candidate
+            .beacons
+            .iter()
+            .map(|coordinates| rotation * coordinates + offset)
+            .collect::<HashSet<Coordinate3D>>();
+        if overlaps(scanner, &offset_set, offset) {
+            return Some(candidate.transformed(rotation, offset));
         }
-        *self = Self([x, y, z]);
     }
+    None
 }
 
-impl Add<Self> for &Coordinate3D {
-    type Output = Coordinate3D;
-
-    fn add(self, rhs: Self) -> Self::Output {
-        Coordinate3D([
-            self.0[0] + rhs.0[0],
-            self.0[1] + rhs.0[1],
-            self.0[2] + rhs.0[2],
-        ])
+fn overlaps(scanner: &Scanner, offset_set: &HashSet<Coordinate3D>, offset: Coordinate3D) -> bool {
+    let detection_range = Cuboid::around(offset, 1000);
+    let mut overlap_count = 0;
+    for absolute_coordinate in scanner.beacons.iter() {
+        if offset_set.contains(absolute_coordinate) {
+            overlap_count += 1;
+        } else if detection_range.contains(&(*absolute_coordinate).into()) {
+            return false;
+        }
     }
+    overlap_count >= 12
 }
 
-impl Sub<Self> for &Coordinate3D {
-    type Output = Coordinate3D;
+const BEACON_COLOUR: [u8; 3] = [255, 255, 255];
+const SCANNER_COLOUR: [u8; 3] = [255, 0, 0];
 
-    fn sub(self, rhs: Self) -> Self::Output {
-        Coordinate3D([
-            self.0[0] - rhs.0[0],
-            self.0[1] - rhs.0[1],
-            self.0[2] - rhs.0[2],
-        ])
-    }
+#[derive(Debug, PartialEq, Eq)]
+struct PointCloud {
+    beacons: HashSet<Coordinate3D>,
+    scanners: HashSet<Coordinate3D>,
 }
 
-impl Add<Coordinate3D> for &Coordinate3D {
-    type Output = Coordinate3D;
-
-    fn add(self, rhs: Coordinate3D) -> Self::Output {
-        Coordinate3D([
-            self.0[0] + rhs.0[0],
-            self.0[1] + rhs.0[1],
-            self.0[2] + rhs.0[2],
-        ])
+impl PointCloud {
+    fn points(&self) -> Vec<(Coordinate3D, [u8; 3])> {
+        let mut points = self
+            .beacons
+            .iter()
+            .map(|beacon| (*beacon, BEACON_COLOUR))
+            .chain(
+                self.scanners
+                    .iter()
+                    .map(|scanner| (*scanner, SCANNER_COLOUR)),
+            )
+            .collect::<Vec<_>>();
+        points.sort_unstable();
+        points
     }
-}
 
-impl Sub<Coordinate3D> for &Coordinate3D {
-    type Output = Coordinate3D;
+    fn to_ply(&self) -> String {
+        let points = self.points();
+        let mut output = format!(
+            "ply\nformat ascii 1.0\nelement vertex {}\n\
+             property int x\nproperty int y\nproperty int z\n\
+             property uchar red\nproperty uchar green\nproperty uchar blue\nend_header\n",
+            points.len()
+        );
+        for (Coordinate3D([x, y, z]), [red, green, blue]) in points {
+            output += &format!("{} {} {} {} {} {}\n", x, y, z, red, green, blue);
+        }
+        output
+    }
 
-    fn sub(self, rhs: Coordinate3D) -> Self::Output {
-        Coordinate3D([
-            self.0[0] - rhs.0[0],
-            self.0[1] - rhs.0[1],
-            self.0[2] - rhs.0[2],
-        ])
+    fn to_xyz(&self) -> String {
+        let mut output = String::new();
+        for (Coordinate3D([x, y, z]), [red, green, blue]) in self.points() {
+            output += &format!("{} {} {} {} {} {}\n", x, y, z, red, green, blue);
+        }
+        output
     }
 }
 
-impl Add<Coordinate3D> for &HashSet<Coordinate3D> {
-    type Output = HashSet<Coordinate3D>;
+impl FromStr for PointCloud {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .peekable();
+        let mut num_vertices = None;
+        if lines.peek() == Some(&"ply") {
+            for line in lines.by_ref() {
+                if line == "end_header" {
+                    break;
+                } else if let Some(count) = line.strip_prefix("element vertex ") {
+                    num_vertices = Some(
+                        count
+                            .parse::<usize>()
+                            .map_err(|_| format!("{} is not a vertex count", count))?,
+                    );
+                }
+            }
+        }
+
+        let mut cloud = PointCloud {
+            beacons: HashSet::new(),
+            scanners: HashSet::new(),
+        };
+        let mut num_points = 0;
+        for line in lines {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() != 3 && fields.len() != 6 {
+                return Err(format!("{} is neither an XYZ nor an XYZRGB point", line));
+            }
+            let mut position = Coordinate3D::ORIGIN;
+            for (axis, field) in fields[..3].iter().enumerate() {
+                position.0[axis] = field
+                    .parse()
+                    .map_err(|_| format!("{} is not an integer coordinate", field))?;
+            }
+            let mut colour = BEACON_COLOUR;
+            for (channel, field) in fields[3..].iter().enumerate() {
+                colour[channel] = field
+                    .parse()
+                    .map_err(|_| format!("{} is not a colour channel", field))?;
+            }
+            if colour == SCANNER_COLOUR {
+                cloud.scanners.insert(position);
+            } else {
+                cloud.beacons.insert(position);
+            }
+            num_points += 1;
+        }
 
-    fn add(self, rhs: Coordinate3D) -> Self::Output {
-        self.iter()
-            .map(|coordinate| coordinate + &rhs)
-            .collect::<HashSet<Coordinate3D>>()
+        match num_vertices {
+            Some(num_vertices) if num_vertices != num_points => Err(format!(
+                "Header declares {} vertices but {} were found",
+                num_vertices, num_points
+            )),
+            _ => Ok(cloud),
+        }
     }
 }
 
@@ -283,18 +477,21 @@ struct Scanner {
     id: u32,
     position: Coordinate3D,
     beacons: HashSet<Coordinate3D>,
-    inner_vectors: HashSet<Coordinate3D>,
+    inner_vectors: HashMap<Coordinate3D, Vec<(Coordinate3D, Coordinate3D)>>,
 }
 
 impl Scanner {
     fn new(id: u32, position: Coordinate3D, beacons: HashSet<Coordinate3D>) -> Self {
-        let mut inner_vectors = HashSet::new();
+        let mut inner_vectors = HashMap::<Coordinate3D, Vec<_>>::new();
         for beacon in beacons.iter() {
             for other in beacons.iter() {
-                if beacon != other {
+                if beacon < other {
                     let mut inner_vector = (beacon - other).abs();
                     inner_vector.normalise();
-                    inner_vectors.insert(inner_vector);
+                    inner_vectors
+                        .entry(inner_vector)
+                        .or_default()
+                        .push((*beacon, *other));
                 }
             }
         }
@@ -313,6 +510,35 @@ impl Scanner {
             inner_vectors,
         }
     }
+
+    fn shared_inner_vectors(&self, other: &Scanner) -> usize {
+        self.inner_vectors
+            .keys()
+            .filter(|inner_vector| other.inner_vectors.contains_key(inner_vector))
+            .count()
+    }
+
+    fn transformed(&self, rotation: RotationMatrix3D, offset: Coordinate3D) -> Scanner {
+        let transform = |coordinate: &Coordinate3D| rotation * coordinate + offset;
+        Scanner {
+            id: self.id,
+            position: offset,
+            beacons: self.beacons.iter().map(transform).collect(),
+            inner_vectors: self
+                .inner_vectors
+                .iter()
+                .map(|(inner_vector, pairs)| {
+                    (
+                        *inner_vector,
+                        pairs
+                            .iter()
+                            .map(|(start, end)| (transform(start), transform(end)))
+                            .collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
 }
 
 impl Hash for Scanner {
@@ -1,152 +1,349 @@
 use lazy_static::lazy_static;
+use num::{BigInt, BigRational, BigUint, ToPrimitive, Zero};
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs::File;
 use std::io::prelude::*;
-use std::iter::successors;
+
+#[derive(Debug, Clone, Copy)]
+struct Game {
+    board_size: u32,
+    die_sides: u32,
+    rolls_per_turn: u32,
+    target_score: u32,
+}
 
 fn main() -> std::io::Result<()> {
-    let mut input_file = File::open(match env::args().skip(1).next() {
+    let (flags, filenames): (Vec<String>, Vec<String>) =
+        env::args().skip(1).partition(|arg| arg.starts_with("--"));
+    let mut input_file = File::open(match filenames.into_iter().next() {
         Some(filename) => filename,
         None => "input.txt".to_string(),
     })?;
     let mut input = String::new();
     input_file.read_to_string(&mut input)?;
-    part1(&input);
-    part2(&input);
+
+    let mut deterministic_game = Game {
+        board_size: 10,
+        die_sides: 100,
+        rolls_per_turn: 3,
+        target_score: 1000,
+    };
+    let mut dirac_game = Game {
+        board_size: 10,
+        die_sides: 3,
+        rolls_per_turn: 3,
+        target_score: 21,
+    };
+    let mut show_probabilities = false;
+    for flag in flags.iter() {
+        let (name, value) = flag.split_once('=').unwrap_or((flag, ""));
+        let settings = match name {
+            "--board" => vec![
+                &mut deterministic_game.board_size,
+                &mut dirac_game.board_size,
+            ],
+            "--rolls" => vec![
+                &mut deterministic_game.rolls_per_turn,
+                &mut dirac_game.rolls_per_turn,
+            ],
+            "--deterministic-die" => vec![&mut deterministic_game.die_sides],
+            "--deterministic-target" => vec![&mut deterministic_game.target_score],
+            "--dirac-die" => vec![&mut dirac_game.die_sides],
+            "--dirac-target" => vec![&mut dirac_game.target_score],
+            "--probabilities" => {
+                show_probabilities = true;
+                continue;
+            }
+            _ => continue,
+        };
+        match value.parse() {
+            Ok(value) => settings.into_iter().for_each(|setting| *setting = value),
+            Err(_) => {
+                println!("{} is not a number for {}", value, name);
+                return Ok(());
+            }
+        }
+    }
+    for (name, game) in [
+        ("deterministic", &deterministic_game),
+        ("Dirac", &dirac_game),
+    ] {
+        if let Err(error) = game.validate() {
+            println!("invalid {} game: {}", name, error);
+            return Ok(());
+        }
+    }
+    let start_positions = match parse_start_positions(&input, deterministic_game.board_size) {
+        Ok(start_positions) => start_positions,
+        Err(error) => {
+            println!("invalid starting positions: {}", error);
+            return Ok(());
+        }
+    };
+    part1(&start_positions, &deterministic_game);
+    part2(&start_positions, &dirac_game, show_probabilities);
     Ok(())
 }
 
-fn part1(input: &str) {
+impl Game {
+    fn validate(&self) -> Result<(), String> {
+        for (setting, value) in [
+            ("board size", self.board_size),
+            ("number of die sides", self.die_sides),
+            ("target score", self.target_score),
+        ] {
+            if value == 0 {
+                return Err(format!("the {} must be positive", setting));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_start_positions(input: &str, board_size: u32) -> Result<Vec<u32>, String> {
     lazy_static! {
         static ref PLAYER_REGEX: Regex =
             Regex::new(r"Player \d+ starting position: (?P<start_pos>\d+)").unwrap();
     }
 
-    let positions: Vec<u32> = PLAYER_REGEX
+    let start_positions = PLAYER_REGEX
         .captures_iter(input)
-        .map(|player_capture| (player_capture["start_pos"].parse::<u32>().unwrap()))
-        .collect();
-    let step_lengths = [vec![6u32, 4, 2, 0, 8], vec![5u32, 3, 1, 9, 7]];
-    let score_gains = [
-        successors(Some((0, positions[0])), |(i, score)| {
-            Some(((i + 1) % 5, (score + step_lengths[0][*i] - 1) % 10 + 1))
-        })
-        .map(|(_, score)| score)
-        .skip(1)
-        .take(10)
-        .collect::<Vec<u32>>(),
-        successors(Some((0, positions[1])), |(i, score)| {
-            Some(((i + 1) % 5, (score + step_lengths[1][*i] - 1) % 10 + 1))
+        .map(|player_capture| {
+            player_capture["start_pos"]
+                .parse::<u32>()
+                .map_err(|error| format!("{}: {}", &player_capture["start_pos"], error))
         })
-        .map(|(_, score)| score)
-        .skip(1)
-        .take(10)
-        .collect::<Vec<u32>>(),
-    ];
-    let winning_player = if score_gains[0].iter().sum::<u32>() > score_gains[1].iter().sum::<u32>()
+        .collect::<Result<Vec<_>, _>>()?;
+    if start_positions.len() < 2 {
+        return Err(format!(
+            "the game needs at least two players, found {}",
+            start_positions.len()
+        ));
+    }
+    if let Some(start_position) = start_positions
+        .iter()
+        .find(|start_position| !(1..=board_size).contains(*start_position))
     {
-        0
-    } else {
-        1
+        return Err(format!(
+            "{} is not a space on a board of size {}",
+            start_position, board_size
+        ));
+    }
+    Ok(start_positions)
+}
+
+fn part1(start_positions: &[u32], game: &Game) {
+    let mut positions = start_positions.to_vec();
+    let mut scores = vec![0; positions.len()];
+    let mut die = (1..=game.die_sides).cycle();
+    let mut num_rolls = 0;
+    let winning_player = 'game: loop {
+        for (player, position) in positions.iter_mut().enumerate() {
+            let step = die
+                .by_ref()
+                .take(game.rolls_per_turn as usize)
+                .fold(0, |step, roll| (step + roll) % game.board_size);
+            num_rolls += game.rolls_per_turn as u64;
+            *position = (*position + step - 1) % game.board_size + 1;
+            scores[player] += *position as u64;
+            if scores[player] >= game.target_score as u64 {
+                break 'game player;
+            }
+        }
     };
-    let whole_10_turns_before_win = 999 / score_gains[winning_player].iter().sum::<u32>();
-    let mut rolls_before_win = whole_10_turns_before_win * 60;
-    let mut player_score = score_gains
+
+    if scores.len() > 2 {
+        for (player, score) in scores.iter().enumerate() {
+            println!("Player {} scored {}", player + 1, score);
+        }
+    }
+    let losing_score = scores
         .iter()
-        .map(|score_per_10_turns| {
-            whole_10_turns_before_win * score_per_10_turns.iter().sum::<u32>()
-        })
-        .collect::<Vec<_>>();
-    'final10: for step in 0..10 {
-        for player in 0..2 {
-            player_score[player] += score_gains[player][step];
-            rolls_before_win += 3;
-            if player_score[player] >= 1000 {
-                break 'final10;
+        .enumerate()
+        .filter(|&(player, _)| player != winning_player)
+        .map(|(_, &score)| score)
+        .min()
+        .unwrap();
+    println!("Part 1: {}", num_rolls * losing_score);
+}
+
+fn roll_distribution(game: &Game) -> Vec<(u32, u64)> {
+    let mut distribution = HashMap::from([(0, 1)]);
+    for _ in 0..game.rolls_per_turn {
+        let mut next_distribution = HashMap::new();
+        for (step, num_universes) in distribution {
+            for roll in 1..=game.die_sides {
+                *next_distribution.entry(step + roll).or_insert(0) += num_universes;
             }
         }
+        distribution = next_distribution;
     }
-
-    println!(
-        "Part 1: {}",
-        rolls_before_win * player_score[1 - winning_player]
-    );
+    let mut distribution = distribution.into_iter().collect::<Vec<_>>();
+    distribution.sort_unstable();
+    distribution
 }
 
-fn part2(input: &str) {
-    lazy_static! {
-        static ref PLAYER_REGEX: Regex =
-            Regex::new(r"Player \d+ starting position: (?P<start_pos>\d+)").unwrap();
+fn part2(start_positions: &[u32], game: &Game, show_probabilities: bool) {
+    let outcome = play_dirac_dice(start_positions, game);
+    let winning_universes = outcome.winning_universes();
+
+    if winning_universes.len() > 2 {
+        for (player, num_universes) in winning_universes.iter().enumerate() {
+            println!("Player {} wins in {} universes", player + 1, num_universes);
+        }
+    }
+    if show_probabilities {
+        for (player, probability) in outcome.win_probabilities().iter().enumerate() {
+            println!(
+                "Player {} wins with probability {} (~{:.6})",
+                player + 1,
+                probability,
+                probability.to_f64().unwrap()
+            );
+        }
+        for (turns, probability) in outcome.game_length_distribution() {
+            println!(
+                "Game ends after {} turns with probability {} (~{:.6})",
+                turns,
+                probability,
+                probability.to_f64().unwrap()
+            );
+        }
+        let expected_turns = outcome.expected_turns();
+        println!(
+            "Expected number of turns: {} (~{:.6})",
+            expected_turns,
+            expected_turns.to_f64().unwrap()
+        );
     }
+    println!("Part 2: {}", winning_universes.iter().max().unwrap());
+}
 
-    let start_positions: Vec<u32> = PLAYER_REGEX
-        .captures_iter(input)
-        .map(|player_capture| (player_capture["start_pos"].parse::<u32>().unwrap()))
-        .collect();
-    let step_weights = [(3, 1), (4, 3), (5, 6), (6, 7), (7, 6), (8, 3), (9, 1)];
-
-    let mut score_weights_after_throws = [
-        vec![HashMap::<(u32, u32), u64>::new()],
-        vec![HashMap::<(u32, u32), u64>::new()],
-    ];
-    score_weights_after_throws[0][0].insert((start_positions[0], 0), 1);
-    score_weights_after_throws[1][0].insert((start_positions[1], 0), 1);
-
-    let mut finished_after_throw = [
-        HashMap::<usize, (u64, u64)>::new(),
-        HashMap::<usize, (u64, u64)>::new(),
-    ];
+fn play_dirac_dice(start_positions: &[u32], game: &Game) -> QuantumOutcome {
+    let step_weights = roll_distribution(game);
+
+    let mut score_weights_after_throws = start_positions
+        .iter()
+        .map(|&start_position| vec![HashMap::from([((start_position, 0), BigUint::from(1u32))])])
+        .collect::<Vec<Vec<HashMap<(u32, u32), BigUint>>>>();
+    let mut finished_after_throw =
+        vec![HashMap::<usize, (BigUint, BigUint)>::new(); start_positions.len()];
 
     for throw_num in 1.. {
-        for player in 0..2 {
+        for (player, score_weights_after_throw) in score_weights_after_throws.iter_mut().enumerate()
+        {
             let mut score_weights = HashMap::new();
             for ((position, score), num_universes) in
-                score_weights_after_throws[player][throw_num - 1].iter()
+                score_weights_after_throw[throw_num - 1].iter()
             {
-                for (step, weight) in step_weights {
-                    let new_position = (position + step - 1) % 10 + 1;
+                for &(step, weight) in step_weights.iter() {
+                    let new_position = (position + step - 1) % game.board_size + 1;
                     let new_score = score + new_position;
-                    if new_score < 21 {
-                        *score_weights.entry((new_position, new_score)).or_insert(0) +=
-                            num_universes * weight;
-                        finished_after_throw[player]
-                            .entry(throw_num)
-                            .or_insert((0, 0))
-                            .1 += num_universes * weight;
+                    let finished = finished_after_throw[player].entry(throw_num).or_default();
+                    if new_score < game.target_score {
+                        *score_weights
+                            .entry((new_position, new_score))
+                            .or_insert_with(BigUint::zero) += num_universes * weight;
+                        finished.1 += num_universes * weight;
                     } else {
-                        finished_after_throw[player]
-                            .entry(throw_num)
-                            .or_insert((0, 0))
-                            .0 += num_universes * weight;
+                        finished.0 += num_universes * weight;
                     }
                 }
             }
-            score_weights_after_throws[player].push(score_weights);
+            score_weights_after_throw.push(score_weights);
         }
 
-        if finished_after_throw[0][&throw_num].1 == 0 && finished_after_throw[1][&throw_num].1 == 0
+        if score_weights_after_throws
+            .iter()
+            .any(|score_weights_after_throw| score_weights_after_throw[throw_num].is_empty())
         {
             break;
         }
     }
 
-    let mut winning_universes = [0, 0];
-    let mut num_universes_still_ongoing = 1;
+    // A player finishing on a throw wins in every combination of the other players' universes
+    // that are still ongoing, i.e. after this throw for earlier players and the previous one for
+    // later players.
+    let mut game_lengths = BTreeMap::new();
+    let mut num_universes_still_ongoing = vec![BigUint::from(1u32); start_positions.len()];
     'universe_counting: for throw_num in 1.. {
-        for player in 0..2 {
-            let (finished, ongoing) = finished_after_throw[player][&throw_num];
-            winning_universes[player] += num_universes_still_ongoing * finished;
-            num_universes_still_ongoing = ongoing;
-            if num_universes_still_ongoing == 0 {
+        for player in 0..start_positions.len() {
+            let (finished, ongoing) = finished_after_throw[player]
+                .remove(&throw_num)
+                .unwrap_or_default();
+            let other_universes_still_ongoing = num_universes_still_ongoing
+                .iter()
+                .enumerate()
+                .filter(|&(other_player, _)| other_player != player)
+                .map(|(_, ongoing)| ongoing)
+                .product::<BigUint>();
+            if !finished.is_zero() {
+                let turns = (throw_num - 1) * start_positions.len() + player + 1;
+                game_lengths.insert(turns, other_universes_still_ongoing * finished);
+            }
+            if ongoing.is_zero() {
                 break 'universe_counting;
             }
+            num_universes_still_ongoing[player] = ongoing;
+        }
+    }
+
+    QuantumOutcome {
+        num_players: start_positions.len(),
+        outcomes_per_turn: BigUint::from(game.die_sides).pow(game.rolls_per_turn),
+        game_lengths,
+    }
+}
+
+// Universes ending after fewer turns have split fewer times, so each one ending after n turns has
+// probability 1 / outcomes_per_turn^n.
+struct QuantumOutcome {
+    num_players: usize,
+    outcomes_per_turn: BigUint,
+    game_lengths: BTreeMap<usize, BigUint>,
+}
+
+impl QuantumOutcome {
+    fn winner(&self, turns: usize) -> usize {
+        (turns - 1) % self.num_players
+    }
+
+    fn probability(&self, num_universes: &BigUint, turns: usize) -> BigRational {
+        BigRational::new(
+            BigInt::from(num_universes.clone()),
+            BigInt::from(self.outcomes_per_turn.pow(turns as u32)),
+        )
+    }
+
+    fn winning_universes(&self) -> Vec<BigUint> {
+        let mut winning_universes = vec![BigUint::zero(); self.num_players];
+        for (&turns, num_universes) in self.game_lengths.iter() {
+            winning_universes[self.winner(turns)] += num_universes;
+        }
+        winning_universes
+    }
+
+    fn win_probabilities(&self) -> Vec<BigRational> {
+        let mut win_probabilities = vec![BigRational::zero(); self.num_players];
+        for (&turns, num_universes) in self.game_lengths.iter() {
+            win_probabilities[self.winner(turns)] += self.probability(num_universes, turns);
         }
+        win_probabilities
+    }
+
+    fn game_length_distribution(&self) -> BTreeMap<usize, BigRational> {
+        self.game_lengths
+            .iter()
+            .map(|(&turns, num_universes)| (turns, self.probability(num_universes, turns)))
+            .collect()
     }
 
-    println!(
-        "Part 2: {}",
-        u64::max(winning_universes[0], winning_universes[1])
-    );
+    fn expected_turns(&self) -> BigRational {
+        self.game_length_distribution()
+            .into_iter()
+            .map(|(turns, probability)| probability * BigInt::from(turns))
+            .sum()
+    }
 }
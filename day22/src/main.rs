@@ -1,101 +1,206 @@
 use crate::Action::*;
+use geometry::Cuboid;
 use lazy_static::lazy_static;
 use regex::{Captures, Regex};
+use std::cell::OnceCell;
 use std::env;
 use std::fs::File;
-use std::io::prelude::*;
+use std::io::{self, prelude::*};
+use std::mem::take;
 use std::str::FromStr;
 
+const INITIALISATION_REGION: Cuboid = Cuboid::new([-50, -50, -50], [50, 50, 50]);
+
 fn main() -> std::io::Result<()> {
-    let mut input_file = File::open(match env::args().skip(1).next() {
+    let (flags, filenames): (Vec<String>, Vec<String>) =
+        env::args().skip(1).partition(|arg| arg.starts_with("--"));
+    if flags.iter().any(|flag| flag == "--interactive") {
+        return interactive_session();
+    }
+    let mut input_file = File::open(match filenames.into_iter().next() {
         Some(filename) => filename,
         None => "input.txt".to_string(),
     })?;
     let mut input = String::new();
     input_file.read_to_string(&mut input)?;
-    part1(&input);
-    part2(&input);
+    let steps = match parse_steps(&input) {
+        Ok(steps) => steps,
+        Err(error) => {
+            println!("invalid reboot step: {}", error);
+            return Ok(());
+        }
+    };
+    if flags.iter().any(|flag| flag == "--sweep") {
+        println!(
+            "Part 1: {}",
+            sweep_volume(&steps, Some(&INITIALISATION_REGION))
+        );
+        println!("Part 2: {}", sweep_volume(&steps, None));
+        return Ok(());
+    }
+    // The shadowing pass grows quadratically with the script, so only run it when it is needed.
+    let shadowing_reactor = OnceCell::new();
+    let reactor = || shadowing_reactor.get_or_init(|| Reactor::reboot(&steps));
+    let disjoint_reactor = OnceCell::new();
+    let disjoint_reactor = || disjoint_reactor.get_or_init(|| DisjointReactor::reboot(&steps));
+    if flags.iter().any(|flag| flag == "--disjoint") {
+        part1(disjoint_reactor());
+        part2(disjoint_reactor());
+        println!(
+            "Disjoint: {} lit cubes in {} cuboids",
+            disjoint_reactor().lit_volume(),
+            disjoint_reactor().lit_cuboids.len()
+        );
+    } else {
+        part1(reactor());
+        part2(reactor());
+    }
+    for flag in flags.iter() {
+        if let Some(filename) = flag.strip_prefix("--export-disjoint=") {
+            let mut output_file = File::create(filename)?;
+            for cuboid in disjoint_reactor().lit_cuboids.iter() {
+                writeln!(output_file, "on {}", cuboid)?;
+            }
+        } else if flag == "--cross-check" {
+            let solvers: [(&str, &dyn ReactorState); 2] =
+                [("shadowing", reactor()), ("disjoint", disjoint_reactor())];
+            for (part, bounds) in [(1, Some(&INITIALISATION_REGION)), (2, None)] {
+                let sweep_volume = sweep_volume(&steps, bounds);
+                let disagreements = solvers
+                    .iter()
+                    .map(|(name, solver)| (name, solver.lit_volume_within(bounds)))
+                    .filter(|(_, volume)| *volume != sweep_volume)
+                    .collect::<Vec<_>>();
+                if disagreements.is_empty() {
+                    println!("Cross-check part {}: all solvers agree", part);
+                }
+                for (name, volume) in disagreements {
+                    println!(
+                        "Cross-check part {}: {} solver gives {} but sweep solver gives {}",
+                        part, name, volume, sweep_volume
+                    );
+                }
+            }
+        } else if flag == "--regions" {
+            for region in reactor().lit_regions() {
+                println!(
+                    "Step {}: {} lit cubes within {}",
+                    region.step + 1,
+                    region.volume(),
+                    region.cuboid
+                );
+            }
+        } else if let Some(coordinate) = flag.strip_prefix("--query=") {
+            match parse_coordinate(coordinate) {
+                Some(coordinate) if reactor().is_lit(&coordinate) => {
+                    println!("Cube {} is on", coordinate_string(&coordinate))
+                }
+                Some(coordinate) => println!("Cube {} is off", coordinate_string(&coordinate)),
+                None => println!("{} is not a coordinate", coordinate),
+            }
+        } else if let Some(bounds) = flag.strip_prefix("--count=") {
+            match BOUNDS_REGEX
+                .captures(bounds)
+                .and_then(|capture| parse_bounds(&capture))
+            {
+                Some(bounds) => println!(
+                    "Lit cubes within {}: {}",
+                    bounds,
+                    reactor().lit_volume_within(Some(&bounds))
+                ),
+                None => println!("{} is not a cuboid", bounds),
+            }
+        }
+    }
     Ok(())
 }
 
-fn part1(input: &str) {
-    lazy_static! {
-        static ref CUBOID_REGEX: Regex =
-            Regex::new(r"(?P<action>on|off) x=(?P<x_start>-?\d+)\.\.(?P<x_end>-?\d+),y=(?P<y_start>-?\d+)\.\.(?P<y_end>-?\d+),z=(?P<z_start>-?\d+)\.\.(?P<z_end>-?\d+)").unwrap();
-    }
-
-    let cuboids = CUBOID_REGEX
-        .captures_iter(input)
-        .map(|capture| parse_cuboid(capture).unwrap())
-        .filter_map(|(action, cuboid)| match Cuboid::new(-50, 50, -50, 50, -50, 50).intersection(&cuboid) {
-            Some(cuboid) => Some((action, cuboid)),
-            None => None,
-        })
-        .collect::<Vec<_>>();
-
-    let mut shadowing_set = Vec::new();
-    let mut unshadowing_set = Vec::new();
-
-    let mut num_active_cubes = 0;
-    for (action, cuboid) in cuboids.into_iter().rev() {
-        let (volume, new_shadowing_set, new_unshadowing_set) =
-            cuboid.unshadowed_volume(shadowing_set, unshadowing_set);
-        if action == On {
-            num_active_cubes += volume;
+fn interactive_session() -> std::io::Result<()> {
+    let mut reactor = DisjointReactor::default();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match CUBOID_REGEX.captures(&line).and_then(parse_cuboid) {
+            Some((action, cuboid)) => {
+                reactor.apply(action, &cuboid);
+                println!("{} lit cubes", reactor.lit_volume());
+            }
+            None => println!("{} is not a reboot step", line.trim()),
         }
-        shadowing_set = new_shadowing_set;
-        unshadowing_set = new_unshadowing_set;
     }
+    Ok(())
+}
 
-    println!("Part 1: {}", num_active_cubes);
+fn part1(reactor: &dyn ReactorState) {
+    println!(
+        "Part 1: {}",
+        reactor.lit_volume_within(Some(&INITIALISATION_REGION))
+    );
 }
 
-fn part2(input: &str) {
-    lazy_static! {
-        static ref CUBOID_REGEX: Regex =
-        Regex::new(r"(?P<action>on|off) x=(?P<x_start>-?\d+)\.\.(?P<x_end>-?\d+),y=(?P<y_start>-?\d+)\.\.(?P<y_end>-?\d+),z=(?P<z_start>-?\d+)\.\.(?P<z_end>-?\d+)").unwrap();
-    }
+fn part2(reactor: &dyn ReactorState) {
+    println!("Part 2: {}", reactor.lit_volume_within(None));
+}
 
-    let cuboids = CUBOID_REGEX
-        .captures_iter(input)
-        .map(|capture| parse_cuboid(capture).unwrap())
-        .collect::<Vec<_>>();
+lazy_static! {
+    static ref CUBOID_REGEX: Regex =
+        Regex::new(r"(?P<action>on|off|toggle) x=(?P<x_start>-?\d+)\.\.(?P<x_end>-?\d+),y=(?P<y_start>-?\d+)\.\.(?P<y_end>-?\d+),z=(?P<z_start>-?\d+)\.\.(?P<z_end>-?\d+)").unwrap();
+    static ref BOUNDS_REGEX: Regex =
+        Regex::new(r"^x=(?P<x_start>-?\d+)\.\.(?P<x_end>-?\d+),y=(?P<y_start>-?\d+)\.\.(?P<y_end>-?\d+),z=(?P<z_start>-?\d+)\.\.(?P<z_end>-?\d+)$").unwrap();
+}
 
-    let mut shadowing_set = Vec::new();
-    let mut unshadowing_set = Vec::new();
+fn parse_steps(input: &str) -> Result<Vec<(Action, Cuboid)>, String> {
+    CUBOID_REGEX
+        .captures_iter(input)
+        .map(|capture| {
+            let step = capture[0].to_string();
+            parse_cuboid(capture).ok_or(format!("{} is reversed or out of range", step))
+        })
+        .collect()
+}
 
-    let mut num_active_cubes = 0;
-    for (action, cuboid) in cuboids.into_iter().rev() {
-        let (volume, new_shadowing_set, new_unshadowing_set) =
-            cuboid.unshadowed_volume(shadowing_set, unshadowing_set);
-        if action == On {
-            num_active_cubes += volume;
-        }
-        shadowing_set = new_shadowing_set;
-        unshadowing_set = new_unshadowing_set;
+fn parse_coordinate(coordinate: &str) -> Option<[i64; 3]> {
+    let mut axes = coordinate.split(',').map(|axis| axis.trim().parse::<i64>());
+    let coordinate = [axes.next()?.ok()?, axes.next()?.ok()?, axes.next()?.ok()?];
+    match axes.next() {
+        Some(_) => None,
+        None => Some(coordinate),
     }
+}
 
-    println!("Part 2: {}", num_active_cubes);
+fn coordinate_string(coordinate: &[i64; 3]) -> String {
+    let [x, y, z] = coordinate;
+    format!("{},{},{}", x, y, z)
 }
 
 fn parse_cuboid(capture: Captures) -> Option<(Action, Cuboid)> {
     let action = Action::from_str(&capture["action"]).ok()?;
-    let x_start = capture["x_start"].parse::<i32>().ok()?;
-    let x_end = capture["x_end"].parse::<i32>().ok()?;
-    let y_start = capture["y_start"].parse::<i32>().ok()?;
-    let y_end = capture["y_end"].parse::<i32>().ok()?;
-    let z_start = capture["z_start"].parse::<i32>().ok()?;
-    let z_end = capture["z_end"].parse::<i32>().ok()?;
-    Some((
-        action,
-        Cuboid::new(x_start, x_end, y_start, y_end, z_start, z_end),
+    Some((action, parse_bounds(&capture)?))
+}
+
+fn parse_bounds(capture: &Captures) -> Option<Cuboid> {
+    let x_start = capture["x_start"].parse::<i64>().ok()?;
+    let x_end = capture["x_end"].parse::<i64>().ok()?;
+    let y_start = capture["y_start"].parse::<i64>().ok()?;
+    let y_end = capture["y_end"].parse::<i64>().ok()?;
+    let z_start = capture["z_start"].parse::<i64>().ok()?;
+    let z_end = capture["z_end"].parse::<i64>().ok()?;
+    if x_start > x_end || y_start > y_end || z_start > z_end {
+        return None;
+    }
+    Some(Cuboid::new(
+        [x_start, y_start, z_start],
+        [x_end, y_end, z_end],
     ))
 }
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Action {
     On,
     Off,
+    Toggle,
 }
 
 impl FromStr for Action {
@@ -105,87 +210,287 @@ impl FromStr for Action {
         match s {
             "on" => Ok(On),
             "off" => Ok(Off),
-            _ => Err(format!("{} is neither on nor off", s)),
+            "toggle" => Ok(Toggle),
+            _ => Err(format!("{} is neither on, off nor toggle", s)),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-struct Cuboid {
-    x_start: i32,
-    x_end: i32,
-    y_start: i32,
-    y_end: i32,
-    z_start: i32,
-    z_end: i32,
+#[derive(Debug)]
+struct LitRegion {
+    step: usize,
+    cuboid: Cuboid,
+    additions: Vec<Cuboid>,
+    subtractions: Vec<Cuboid>,
+}
+
+impl LitRegion {
+    fn volume(&self) -> u128 {
+        self.lit_volume_within(&self.cuboid)
+    }
+
+    fn lit_volume_within(&self, bounds: &Cuboid) -> u128 {
+        let volume_within = |cuboids: &Vec<Cuboid>| {
+            cuboids
+                .iter()
+                .filter_map(|cuboid| cuboid.intersection(bounds))
+                .map(|cuboid| cuboid.volume())
+                .sum::<u128>()
+        };
+        volume_within(&self.additions) - volume_within(&self.subtractions)
+    }
+
+    fn is_lit(&self, coordinate: &[i64; 3]) -> bool {
+        let count_containing = |cuboids: &Vec<Cuboid>| {
+            cuboids
+                .iter()
+                .filter(|cuboid| cuboid.contains(coordinate))
+                .count()
+        };
+        count_containing(&self.additions) > count_containing(&self.subtractions)
+    }
+}
+
+trait ReactorState {
+    fn lit_volume_within(&self, bounds: Option<&Cuboid>) -> u128;
+}
+
+#[derive(Debug)]
+struct Reactor {
+    regions: Vec<LitRegion>,
 }
 
-impl Cuboid {
-    fn new(x_start: i32, x_end: i32, y_start: i32, y_end: i32, z_start: i32, z_end: i32) -> Cuboid {
-        Cuboid {
-            x_start,
-            x_end,
-            y_start,
-            y_end,
-            z_start,
-            z_end,
+impl Reactor {
+    fn reboot(steps: &[(Action, Cuboid)]) -> Reactor {
+        let mut shadowing_set = Vec::new();
+        let mut unshadowing_set = Vec::new();
+
+        let mut regions = Vec::new();
+        for (step, action, cuboid) in resolve_toggles(steps).iter().rev() {
+            let (additions, subtractions) =
+                unshadowed_volume(cuboid, &mut shadowing_set, &mut unshadowing_set);
+            if *action == On {
+                regions.push(LitRegion {
+                    step: *step,
+                    cuboid: *cuboid,
+                    additions,
+                    subtractions,
+                });
+            }
         }
+        regions.reverse();
+
+        Reactor { regions }
+    }
+
+    fn lit_regions(&self) -> impl Iterator<Item = &LitRegion> {
+        self.regions.iter().filter(|region| region.volume() > 0)
+    }
+
+    fn is_lit(&self, coordinate: &[i64; 3]) -> bool {
+        self.regions
+            .iter()
+            .filter(|region| region.cuboid.contains(coordinate))
+            .any(|region| region.is_lit(coordinate))
     }
+}
 
-    fn overlaps(&self, cuboid: &Self) -> bool {
-        let x_overlap = self.x_end >= cuboid.x_start && cuboid.x_end >= self.x_start;
-        let y_overlap = self.y_end >= cuboid.y_start && cuboid.y_end >= self.y_start;
-        let z_overlap = self.z_end >= cuboid.z_start && cuboid.z_end >= self.z_start;
-        x_overlap && y_overlap && z_overlap
+impl ReactorState for Reactor {
+    fn lit_volume_within(&self, bounds: Option<&Cuboid>) -> u128 {
+        match bounds {
+            Some(bounds) => self
+                .regions
+                .iter()
+                .filter(|region| region.cuboid.overlaps(bounds))
+                .map(|region| region.lit_volume_within(bounds))
+                .sum(),
+            None => self.regions.iter().map(LitRegion::volume).sum(),
+        }
     }
+}
 
-    fn volume(&self) -> usize {
-        (self.x_end + 1 - self.x_start) as usize
-            * (self.y_end + 1 - self.y_start) as usize
-            * (self.z_end + 1 - self.z_start) as usize
+// The shadowing pass only distinguishes on from off, so each toggle is replaced by switching its
+// cuboid off and then on again wherever it was unlit before the toggle.
+fn resolve_toggles(steps: &[(Action, Cuboid)]) -> Vec<(usize, Action, Cuboid)> {
+    if !steps.iter().any(|(action, _)| *action == Toggle) {
+        return steps
+            .iter()
+            .enumerate()
+            .map(|(step, (action, cuboid))| (step, *action, *cuboid))
+            .collect();
     }
 
-    fn intersection(&self, cuboid: &Cuboid) -> Option<Cuboid> {
-        if self.overlaps(cuboid) {
-            Some(Cuboid::new(
-                i32::max(self.x_start, cuboid.x_start),
-                i32::min(self.x_end, cuboid.x_end),
-                i32::max(self.y_start, cuboid.y_start),
-                i32::min(self.y_end, cuboid.y_end),
-                i32::max(self.z_start, cuboid.z_start),
-                i32::min(self.z_end, cuboid.z_end),
-            ))
+    let mut disjoint_reactor = DisjointReactor::default();
+    let mut resolved_steps = Vec::new();
+    for (step, (action, cuboid)) in steps.iter().enumerate() {
+        if *action == Toggle {
+            resolved_steps.push((step, Off, *cuboid));
+            for unlit_cuboid in disjoint_reactor.unlit_within(cuboid) {
+                resolved_steps.push((step, On, unlit_cuboid));
+            }
         } else {
-            None
+            resolved_steps.push((step, *action, *cuboid));
+        }
+        disjoint_reactor.apply(*action, cuboid);
+    }
+    resolved_steps
+}
+
+#[derive(Debug, Default)]
+struct DisjointReactor {
+    lit_cuboids: Vec<Cuboid>,
+}
+
+impl DisjointReactor {
+    fn reboot(steps: &[(Action, Cuboid)]) -> DisjointReactor {
+        let mut reactor = DisjointReactor::default();
+        for (action, cuboid) in steps.iter() {
+            reactor.apply(*action, cuboid);
+        }
+        reactor
+    }
+
+    fn apply(&mut self, action: Action, cuboid: &Cuboid) {
+        let unlit_cuboids = match action {
+            Toggle => self.unlit_within(cuboid),
+            _ => Vec::new(),
+        };
+        self.lit_cuboids = take(&mut self.lit_cuboids)
+            .into_iter()
+            .flat_map(|lit_cuboid| lit_cuboid.difference(cuboid))
+            .collect();
+        match action {
+            On => self.lit_cuboids.push(*cuboid),
+            Off => (),
+            Toggle => self.lit_cuboids.extend(unlit_cuboids),
+        }
+    }
+
+    fn unlit_within(&self, cuboid: &Cuboid) -> Vec<Cuboid> {
+        let mut unlit_cuboids = vec![*cuboid];
+        for lit_cuboid in self.lit_cuboids.iter().filter(|lit| lit.overlaps(cuboid)) {
+            unlit_cuboids = unlit_cuboids
+                .into_iter()
+                .flat_map(|unlit_cuboid| unlit_cuboid.difference(lit_cuboid))
+                .collect();
+        }
+        unlit_cuboids
+    }
+
+    fn lit_volume(&self) -> u128 {
+        self.lit_cuboids.iter().map(Cuboid::volume).sum()
+    }
+}
+
+impl ReactorState for DisjointReactor {
+    fn lit_volume_within(&self, bounds: Option<&Cuboid>) -> u128 {
+        match bounds {
+            Some(bounds) => self
+                .lit_cuboids
+                .iter()
+                .filter_map(|cuboid| cuboid.intersection(bounds))
+                .map(|cuboid| cuboid.volume())
+                .sum(),
+            None => self.lit_volume(),
         }
     }
+}
+
+fn sweep_volume(steps: &[(Action, Cuboid)], bounds: Option<&Cuboid>) -> u128 {
+    let steps = steps
+        .iter()
+        .filter_map(|(action, cuboid)| match bounds {
+            Some(bounds) => Some((action, cuboid.intersection(bounds)?)),
+            None => Some((action, *cuboid)),
+        })
+        .collect::<Vec<_>>();
+    // Extents are half-open and widened to i128, so that max + 1 fits even for i64::MAX.
+    let extent =
+        |cuboid: &Cuboid, axis: usize| (cuboid.min[axis] as i128, cuboid.max[axis] as i128 + 1);
 
-    fn unshadowed_volume(
-        &self,
-        shadowing_set: Vec<Cuboid>,
-        unshadowing_set: Vec<Cuboid>,
-    ) -> (usize, Vec<Cuboid>, Vec<Cuboid>) {
-        let shadowing_intersections = shadowing_set
+    let mut x_boundaries = steps
+        .iter()
+        .flat_map(|(_, cuboid)| {
+            let (start, end) = extent(cuboid, 0);
+            [start, end]
+        })
+        .collect::<Vec<_>>();
+    x_boundaries.sort_unstable();
+    x_boundaries.dedup();
+
+    let mut volume = 0;
+    for slab in x_boundaries.windows(2) {
+        let slab_steps = steps
             .iter()
-            .filter_map(|cuboid| self.intersection(cuboid))
+            .filter(|(_, cuboid)| {
+                let (start, end) = extent(cuboid, 0);
+                start <= slab[0] && slab[1] <= end
+            })
+            .map(|(action, cuboid)| (*action, [extent(cuboid, 1), extent(cuboid, 2)]))
             .collect::<Vec<_>>();
-        let mut unshadowing_intersections = unshadowing_set
+        volume += (slab[1] - slab[0]) as u128 * sweep_area(&slab_steps);
+    }
+    volume
+}
+
+fn sweep_area(steps: &[(&Action, [(i128, i128); 2])]) -> u128 {
+    let compress = |axis: usize| {
+        let mut boundaries = steps
             .iter()
-            .filter_map(|cuboid| self.intersection(cuboid))
+            .flat_map(|(_, rectangle)| [rectangle[axis].0, rectangle[axis].1])
             .collect::<Vec<_>>();
-        unshadowing_intersections.push(*self);
-        let volume = unshadowing_intersections
-            .iter()
-            .map(Cuboid::volume)
-            .sum::<usize>()
-            - shadowing_intersections
-                .iter()
-                .map(Cuboid::volume)
-                .sum::<usize>();
-        (
-            volume,
-            shadowing_set.into_iter().chain(unshadowing_intersections.into_iter()).collect::<Vec<_>>(),
-            unshadowing_set.into_iter().chain(shadowing_intersections.into_iter()).collect::<Vec<_>>(),
-        )
+        boundaries.sort_unstable();
+        boundaries.dedup();
+        boundaries
+    };
+    let y_boundaries = compress(0);
+    let z_boundaries = compress(1);
+    if y_boundaries.is_empty() {
+        return 0;
+    }
+
+    let mut lit = vec![vec![false; z_boundaries.len() - 1]; y_boundaries.len() - 1];
+    for (action, [(y_start, y_end), (z_start, z_end)]) in steps.iter() {
+        let y_range = y_boundaries.binary_search(y_start).unwrap()
+            ..y_boundaries.binary_search(y_end).unwrap();
+        let z_range = z_boundaries.binary_search(z_start).unwrap()
+            ..z_boundaries.binary_search(z_end).unwrap();
+        for row in lit[y_range].iter_mut() {
+            for cell in row[z_range.clone()].iter_mut() {
+                *cell = match action {
+                    On => true,
+                    Off => false,
+                    Toggle => !*cell,
+                };
+            }
+        }
+    }
+
+    let mut area = 0;
+    for (y, row) in lit.iter().enumerate() {
+        let height = (y_boundaries[y + 1] - y_boundaries[y]) as u128;
+        for (z, _) in row.iter().enumerate().filter(|(_, cell)| **cell) {
+            area += height * (z_boundaries[z + 1] - z_boundaries[z]) as u128;
+        }
     }
+    area
+}
+
+fn unshadowed_volume(
+    cuboid: &Cuboid,
+    shadowing_set: &mut Vec<Cuboid>,
+    unshadowing_set: &mut Vec<Cuboid>,
+) -> (Vec<Cuboid>, Vec<Cuboid>) {
+    let shadowing_intersections = shadowing_set
+        .iter()
+        .filter_map(|other| cuboid.intersection(other))
+        .collect::<Vec<_>>();
+    let mut unshadowing_intersections = unshadowing_set
+        .iter()
+        .filter_map(|other| cuboid.intersection(other))
+        .collect::<Vec<_>>();
+    unshadowing_intersections.push(*cuboid);
+    shadowing_set.extend(unshadowing_intersections.iter().copied());
+    unshadowing_set.extend(shadowing_intersections.iter().copied());
+    (unshadowing_intersections, shadowing_intersections)
 }
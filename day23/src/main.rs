@@ -1,197 +1,597 @@
 use crate::PossibleMove::*;
-use lazy_static::lazy_static;
+use crate::Search::*;
+use crate::SearchResult::*;
 
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
 use std::{env, vec};
 
+const FOLDED_LINES: [&str; 2] = ["  #D#C#B#A#", "  #D#B#A#C#"];
+
+#[derive(Debug)]
+struct Options {
+    search: Search,
+    show_moves: bool,
+    move_costs: Option<String>,
+    unfolded_lines: Vec<String>,
+}
+
 fn main() -> std::io::Result<()> {
-    let mut input_file = File::open(match env::args().skip(1).next() {
+    let (flags, filenames): (Vec<String>, Vec<String>) =
+        env::args().skip(1).partition(|arg| arg.starts_with("--"));
+    let mut input_file = File::open(match filenames.into_iter().next() {
         Some(filename) => filename,
         None => "input.txt".to_string(),
     })?;
     let mut input = String::new();
     input_file.read_to_string(&mut input)?;
-    part1(&input);
-    part2(&input);
+
+    let mut options = Options {
+        search: AStar,
+        show_moves: false,
+        move_costs: None,
+        unfolded_lines: FOLDED_LINES.iter().map(|line| line.to_string()).collect(),
+    };
+    for flag in flags.iter() {
+        if flag == "--dijkstra" {
+            options.search = Dijkstra;
+        } else if flag == "--show-moves" {
+            options.show_moves = true;
+        } else if let Some(move_costs) = flag.strip_prefix("--costs=") {
+            options.move_costs = Some(move_costs.to_string());
+        } else if let Some(unfolded_lines) = flag.strip_prefix("--unfold=") {
+            options.unfolded_lines = unfolded_lines
+                .split(',')
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+    }
+    part1(&input, &options);
+    part2(&input, &options);
     Ok(())
 }
 
-fn part1(input: &str) {
-    let playboard: Vec<Vec<char>> = input
-        .lines()
-        .map(|line| line.trim_end().chars().collect())
-        .collect();
-    let mut wait_spots: HashMap<usize, Option<char>> = HashMap::new();
-    let mut rooms: HashMap<char, (usize, Vec<char>)> = HashMap::new();
-    let mut unknown_room_position = 'A'..='D';
-    let mut room_ownership = HashMap::new();
-    for y in 0..playboard.len() {
-        for x in 0..playboard[y].len() {
-            match playboard[y][x] {
-                '.' if playboard[y + 1][x] == '#' => {
-                    wait_spots.insert(x, None);
-                }
-                amphipod if ('A'..='D').contains(&amphipod) => {
-                    let room_owner = *room_ownership
-                        .entry(x)
-                        .or_insert_with(|| unknown_room_position.next().unwrap());
-                    rooms
-                        .entry(room_owner)
-                        .and_modify(|(_, occupants)| occupants.push(amphipod))
-                        .or_insert((x, vec![amphipod]));
-                }
-                _ => (),
+fn part1(input: &str, options: &Options) {
+    solve(
+        1,
+        parse_burrow(input, &[], options.move_costs.as_deref()),
+        options,
+    );
+}
+
+fn part2(input: &str, options: &Options) {
+    solve(
+        2,
+        parse_burrow(
+            input,
+            &options.unfolded_lines,
+            options.move_costs.as_deref(),
+        ),
+        options,
+    );
+}
+
+fn solve(part: usize, burrow: Result<(Burrow, BurrowState), String>, options: &Options) {
+    let (burrow, initial_state) = match burrow {
+        Ok(burrow) => burrow,
+        Err(error) => {
+            println!("Part {}: invalid burrow: {}", part, error);
+            return;
+        }
+    };
+    match find_cheapest_solution(&burrow, &initial_state, options.search) {
+        Solved {
+            cost,
+            moves,
+            explored_states,
+        } => {
+            if options.show_moves {
+                print_solution(&burrow, &initial_state, &moves);
+                println!("Explored {} states", explored_states);
             }
+            println!("Part {}: {}", part, cost);
         }
+        Unsolvable { explored_states } => println!(
+            "Part {}: no solution after exploring {} states",
+            part, explored_states
+        ),
     }
-    for (_, (_, occupants)) in rooms.iter_mut() {
-        occupants.reverse();
-    }
-    let room_depth = rooms[&'A'].1.len();
+}
 
-    println!(
-        "Part 1: {}",
-        find_cheapest_solution(&wait_spots, &rooms, room_depth)
-            .unwrap()
-            .0
-    );
+fn parse_move_costs(move_costs: &str) -> Result<Vec<usize>, String> {
+    move_costs
+        .split(',')
+        .map(|cost| {
+            cost.trim()
+                .parse()
+                .map_err(|_| format!("{} is not a move cost", cost.trim()))
+        })
+        .collect()
 }
 
-fn part2(input: &str) {
-    let mut folded_bit = Some("  #D#C#B#A#\n  #D#B#A#C#");
+fn parse_burrow(
+    input: &str,
+    unfolded_lines: &[String],
+    move_costs: Option<&str>,
+) -> Result<(Burrow, BurrowState), String> {
+    let mut unfolded_lines = Some(unfolded_lines);
     let mut playboard_lines = vec![];
     for line in input.lines() {
         playboard_lines.push(line);
-        if line.contains('A') || line.contains('C') || line.contains('B') || line.contains('D') {
-            for extra_line in folded_bit
-                .into_iter()
-                .flat_map(|extra_input| extra_input.lines())
-            {
+        if line.chars().any(|space| space.is_ascii_uppercase()) {
+            for extra_line in unfolded_lines.take().into_iter().flatten() {
                 playboard_lines.push(extra_line);
             }
-            folded_bit = None;
         }
     }
-
     let playboard: Vec<Vec<char>> = playboard_lines
         .into_iter()
         .map(|line| line.trim_end().chars().collect())
         .collect();
-    let mut wait_spots: HashMap<usize, Option<char>> = HashMap::new();
-    let mut rooms: HashMap<char, (usize, Vec<char>)> = HashMap::new();
-    let mut unknown_room_position = 'A'..='D';
-    let mut room_ownership = HashMap::new();
-    for y in 0..playboard.len() {
-        for x in 0..playboard[y].len() {
-            match playboard[y][x] {
-                '.' if playboard[y + 1][x] == '#' => {
-                    wait_spots.insert(x, None);
-                }
-                amphipod if ('A'..='D').contains(&amphipod) => {
-                    let room_owner = *room_ownership
-                        .entry(x)
-                        .or_insert_with(|| unknown_room_position.next().unwrap());
-                    rooms
-                        .entry(room_owner)
-                        .and_modify(|(_, occupants)| occupants.push(amphipod))
-                        .or_insert((x, vec![amphipod]));
+
+    let corridor_y = playboard
+        .iter()
+        .position(|row| row.contains(&(EMPTY as char)))
+        .ok_or("no corridor found")?;
+    let mut wait_spots = vec![];
+    let mut initial_wait_spots = vec![];
+    let mut room_columns: BTreeMap<usize, Vec<(usize, char)>> = BTreeMap::new();
+    for (y, row) in playboard.iter().enumerate().skip(corridor_y) {
+        for (x, &space) in row.iter().enumerate() {
+            if space != EMPTY as char && !space.is_ascii_uppercase() {
+                continue;
+            }
+            if y == corridor_y {
+                // Amphipods may stop anywhere in the corridor except right outside a room.
+                let room_below = match playboard.get(y + 1).and_then(|row| row.get(x)) {
+                    Some(&below) => below == EMPTY as char || below.is_ascii_uppercase(),
+                    None => false,
+                };
+                if !room_below {
+                    wait_spots.push(x);
+                    initial_wait_spots.push(space as u8);
                 }
-                _ => (),
+            } else {
+                room_columns.entry(x).or_default().push((y, space));
             }
         }
     }
-    for (_, (_, occupants)) in rooms.iter_mut() {
-        occupants.reverse();
+
+    if room_columns.is_empty() || room_columns.len() > 26 {
+        return Err(format!("found {} rooms", room_columns.len()));
+    }
+    let room_depth = room_columns.values().map(Vec::len).max().unwrap();
+    for (&x, column) in room_columns.iter() {
+        if playboard[corridor_y].get(x) != Some(&(EMPTY as char)) {
+            return Err(format!("room at {} does not open onto the corridor", x));
+        }
+        if column
+            .iter()
+            .enumerate()
+            .any(|(i, (y, _))| *y != corridor_y + 1 + i)
+        {
+            return Err(format!("room at {} is not a single column", x));
+        }
+        if column.len() != room_depth {
+            return Err(format!(
+                "room at {} is {} deep but other rooms are {} deep",
+                x,
+                column.len(),
+                room_depth
+            ));
+        }
+        if column
+            .windows(2)
+            .any(|pair| pair[0].1 != EMPTY as char && pair[1].1 == EMPTY as char)
+        {
+            return Err(format!(
+                "room at {} has an amphipod above an empty space",
+                x
+            ));
+        }
+    }
+
+    let rooms = room_columns
+        .keys()
+        .enumerate()
+        .map(|(i, &x)| ((b'A' + i as u8) as char, x))
+        .collect::<Vec<_>>();
+    let mut amphipod_counts: HashMap<char, usize> =
+        rooms.iter().map(|&(owner, _)| (owner, 0)).collect();
+    for amphipod in initial_wait_spots
+        .iter()
+        .map(|&space| space as char)
+        .chain(room_columns.values().flatten().map(|&(_, space)| space))
+        .filter(|&space| space != EMPTY as char)
+    {
+        *amphipod_counts
+            .get_mut(&amphipod)
+            .ok_or(format!("amphipod {} has no room", amphipod))? += 1;
+    }
+    for (amphipod, count) in amphipod_counts.into_iter().collect::<BTreeMap<_, _>>() {
+        if count != room_depth {
+            return Err(format!(
+                "found {} amphipods of type {} but rooms are {} deep",
+                count, amphipod, room_depth
+            ));
+        }
+    }
+    let move_costs = move_costs.map(parse_move_costs).transpose()?;
+    if let Some(move_costs) = &move_costs {
+        if move_costs.len() != rooms.len() {
+            return Err(format!(
+                "{} move costs given for {} amphipod types",
+                move_costs.len(),
+                rooms.len()
+            ));
+        }
     }
-    let room_depth = rooms[&'A'].1.len();
 
-    println!(
-        "Part 2: {}",
-        find_cheapest_solution(&wait_spots, &rooms, room_depth)
-            .unwrap()
-            .0
-    );
+    let burrow = Burrow {
+        wait_spots,
+        room_depth,
+        move_costs: match move_costs {
+            Some(move_costs) => move_costs,
+            None => (0..rooms.len()).map(|i| 10usize.pow(i as u32)).collect(),
+        },
+        rooms,
+    };
+
+    let mut state = BurrowState(initial_wait_spots);
+    for column in room_columns.values() {
+        let mut room = column
+            .iter()
+            .rev()
+            .map(|&(_, space)| space as u8)
+            .collect::<Vec<_>>();
+        room.resize(burrow.room_depth, EMPTY);
+        state.0.extend(room);
+    }
+    Ok((burrow, state))
+}
+
+fn print_solution(burrow: &Burrow, initial_state: &BurrowState, moves: &[PossibleMove]) {
+    let mut state = initial_state.clone();
+    println!("{}", burrow.render(&state));
+    let mut total_cost = 0;
+    for (i, possible_move) in moves.iter().enumerate() {
+        state = burrow.apply(&state, possible_move);
+        total_cost += burrow.move_cost(possible_move);
+        println!(
+            "Move {}: {} ({} energy, {} total)",
+            i + 1,
+            possible_move,
+            burrow.move_cost(possible_move),
+            total_cost
+        );
+        println!("{}", burrow.render(&state));
+    }
 }
 
 fn find_cheapest_solution(
-    wait_spots: &HashMap<usize, Option<char>>,
-    rooms: &HashMap<char, (usize, Vec<char>)>,
+    burrow: &Burrow,
+    initial_state: &BurrowState,
+    search: Search,
+) -> SearchResult {
+    let initial_state = initial_state.clone();
+    let mut lowest_costs = HashMap::from([(initial_state.clone(), 0)]);
+    let mut previous_states = HashMap::<BurrowState, (BurrowState, PossibleMove)>::new();
+    let mut open_set = BinaryHeap::new();
+    open_set.push(Reverse((
+        burrow.lower_bound(&initial_state, search),
+        0,
+        initial_state,
+    )));
+
+    let mut explored_states = 0;
+    while let Some(Reverse((_, cost, state))) = open_set.pop() {
+        if lowest_costs[&state] < cost {
+            continue;
+        }
+        explored_states += 1;
+        if burrow.is_solved(&state) {
+            let mut moves = vec![];
+            let mut current_state = &state;
+            while let Some((previous_state, possible_move)) = previous_states.get(current_state) {
+                moves.push(*possible_move);
+                current_state = previous_state;
+            }
+            moves.reverse();
+            return Solved {
+                cost,
+                moves,
+                explored_states,
+            };
+        }
+
+        for (possible_move, next_state) in burrow.possible_moves(&state) {
+            let next_cost = cost + burrow.move_cost(&possible_move);
+            if lowest_costs
+                .get(&next_state)
+                .is_none_or(|&lowest_cost| next_cost < lowest_cost)
+            {
+                lowest_costs.insert(next_state.clone(), next_cost);
+                previous_states.insert(next_state.clone(), (state.clone(), possible_move));
+                open_set.push(Reverse((
+                    next_cost + burrow.lower_bound(&next_state, search),
+                    next_cost,
+                    next_state,
+                )));
+            }
+        }
+    }
+    Unsolvable { explored_states }
+}
+
+#[derive(Debug)]
+enum SearchResult {
+    Solved {
+        cost: usize,
+        moves: Vec<PossibleMove>,
+        explored_states: usize,
+    },
+    Unsolvable {
+        explored_states: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Search {
+    Dijkstra,
+    AStar,
+}
+
+const EMPTY: u8 = b'.';
+
+// Wait spots first, then each room from the bottom up, one byte per space.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct BurrowState(Vec<u8>);
+
+#[derive(Debug)]
+struct Burrow {
+    wait_spots: Vec<usize>,
+    rooms: Vec<(char, usize)>,
     room_depth: usize,
-) -> Option<(usize, Vec<PossibleMove>)> {
-    lazy_static! {
-        static ref MOVE_COSTS: HashMap<char, usize> =
-            [('A', 1), ('B', 10), ('C', 100), ('D', 1000)]
-                .into_iter()
-                .collect::<HashMap<_, _>>();
+    move_costs: Vec<usize>,
+}
+
+impl Burrow {
+    fn move_cost(&self, possible_move: &PossibleMove) -> usize {
+        let (RoomToRoom {
+            amphipod, steps, ..
+        }
+        | CorridorToRoom {
+            amphipod, steps, ..
+        }
+        | RoomToCorridor {
+            amphipod, steps, ..
+        }) = *possible_move;
+        steps * self.move_costs[self.destination(amphipod as u8)]
     }
 
-    let possible_moves = enumerate_possible_moves(&wait_spots, &rooms, room_depth);
-    if possible_moves.is_empty() && wait_spots.values().all(Option::is_none) {
-        return Some((0, vec![]));
+    fn room_offset(&self, room: usize) -> usize {
+        self.wait_spots.len() + room * self.room_depth
     }
 
-    let mut cheapest_solution = None;
-    for possible_move in possible_moves {
-        let mut cost;
-        let mut new_wait_spots = wait_spots.clone();
-        let mut new_rooms = rooms.clone();
-        match possible_move {
-            RoomToRoom {
-                amphipod,
-                room,
-                steps,
-            } => {
-                new_rooms.entry(room).and_modify(|(_, occupants)| {
-                    occupants.pop();
+    fn occupants<'a>(&self, state: &'a BurrowState, room: usize) -> &'a [u8] {
+        let offset = self.room_offset(room);
+        let num_occupants = state.0[offset..offset + self.room_depth]
+            .iter()
+            .take_while(|&&space| space != EMPTY)
+            .count();
+        &state.0[offset..offset + num_occupants]
+    }
+
+    fn destination(&self, amphipod: u8) -> usize {
+        self.rooms
+            .iter()
+            .position(|(owner, _)| *owner as u8 == amphipod)
+            .unwrap()
+    }
+
+    fn accepts(&self, state: &BurrowState, room: usize) -> bool {
+        let occupants = self.occupants(state, room);
+        occupants.len() < self.room_depth
+            && occupants
+                .iter()
+                .all(|&occupant| occupant == self.rooms[room].0 as u8)
+    }
+
+    fn is_clear(&self, state: &BurrowState, from: usize, to: usize) -> bool {
+        let (left, right) = (usize::min(from, to), usize::max(from, to));
+        self.wait_spots
+            .iter()
+            .enumerate()
+            .filter(|(_, &x)| left < x && x < right)
+            .all(|(i, _)| state.0[i] == EMPTY)
+    }
+
+    fn is_solved(&self, state: &BurrowState) -> bool {
+        (0..self.rooms.len()).all(|room| {
+            let occupants = self.occupants(state, room);
+            occupants.len() == self.room_depth
+                && occupants
+                    .iter()
+                    .all(|&occupant| occupant == self.rooms[room].0 as u8)
+        })
+    }
+
+    fn possible_moves(&self, state: &BurrowState) -> Vec<(PossibleMove, BurrowState)> {
+        let mut possible_moves = vec![];
+        for (room, &(owner, position)) in self.rooms.iter().enumerate() {
+            let occupants = self.occupants(state, room);
+            if occupants.iter().all(|&occupant| occupant == owner as u8) {
+                continue;
+            }
+            let amphipod = *occupants.last().unwrap();
+            let exit_steps = self.room_depth + 1 - occupants.len();
+
+            let destination = self.destination(amphipod);
+            let destination_position = self.rooms[destination].1;
+            if destination != room
+                && self.accepts(state, destination)
+                && self.is_clear(state, position, destination_position)
+            {
+                possible_moves.push(RoomToRoom {
+                    amphipod: amphipod as char,
+                    room: owner,
+                    steps: exit_steps + position.abs_diff(destination_position) + self.room_depth
+                        - self.occupants(state, destination).len(),
                 });
-                new_rooms.entry(amphipod).and_modify(|(_, occupants)| {
-                    occupants.push(amphipod);
+            }
+
+            for (i, &x) in self.wait_spots.iter().enumerate() {
+                if state.0[i] == EMPTY && self.is_clear(state, position, x) {
+                    possible_moves.push(RoomToCorridor {
+                        amphipod: amphipod as char,
+                        room: owner,
+                        wait_spot: x,
+                        steps: exit_steps + position.abs_diff(x),
+                    });
+                }
+            }
+        }
+
+        for (i, &x) in self.wait_spots.iter().enumerate() {
+            let amphipod = state.0[i];
+            if amphipod == EMPTY {
+                continue;
+            }
+            let destination = self.destination(amphipod);
+            let destination_position = self.rooms[destination].1;
+            if self.accepts(state, destination) && self.is_clear(state, x, destination_position) {
+                possible_moves.push(CorridorToRoom {
+                    amphipod: amphipod as char,
+                    wait_spot: x,
+                    steps: x.abs_diff(destination_position) + self.room_depth
+                        - self.occupants(state, destination).len(),
                 });
-                cost = steps * MOVE_COSTS[&amphipod];
             }
+        }
+        possible_moves
+            .into_iter()
+            .map(|possible_move| (possible_move, self.apply(state, &possible_move)))
+            .collect()
+    }
+
+    fn apply(&self, state: &BurrowState, possible_move: &PossibleMove) -> BurrowState {
+        let mut next_state = state.clone();
+        let (amphipod, source, target_room) = match *possible_move {
+            RoomToRoom { amphipod, room, .. } => (amphipod, self.top(state, room), Some(amphipod)),
             CorridorToRoom {
                 amphipod,
                 wait_spot,
-                steps,
-            } => {
-                new_wait_spots.insert(wait_spot, None);
-                new_rooms.entry(amphipod).and_modify(|(_, occupants)| {
-                    occupants.push(amphipod);
-                });
-                cost = steps * MOVE_COSTS[&amphipod];
-            }
+                ..
+            } => (amphipod, self.wait_spot_index(wait_spot), Some(amphipod)),
             RoomToCorridor {
                 amphipod,
                 room,
                 wait_spot,
-                steps,
+                ..
             } => {
-                new_wait_spots.insert(wait_spot, Some(amphipod));
-                new_rooms.entry(room).and_modify(|(_, occupants)| {
-                    occupants.pop();
-                });
-                cost = steps * MOVE_COSTS[&amphipod];
+                next_state.0[self.wait_spot_index(wait_spot)] = amphipod as u8;
+                (amphipod, self.top(state, room), None)
             }
+        };
+        next_state.0[source] = EMPTY;
+        if let Some(owner) = target_room {
+            let destination = self.destination(owner as u8);
+            let num_occupants = self.occupants(state, destination).len();
+            next_state.0[self.room_offset(destination) + num_occupants] = amphipod as u8;
         }
+        next_state
+    }
 
-        if let Some((cheapest_subsolution, mut moves)) =
-            find_cheapest_solution(&new_wait_spots, &new_rooms, room_depth)
-        {
-            cost += cheapest_subsolution;
-            moves.push(possible_move);
-            match possible_move {
-                RoomToCorridor { .. } => (),
-                _ => return Some((cost, moves)),
+    fn top(&self, state: &BurrowState, owner: char) -> usize {
+        let room = self.destination(owner as u8);
+        self.room_offset(room) + self.occupants(state, room).len() - 1
+    }
+
+    fn wait_spot_index(&self, wait_spot: usize) -> usize {
+        self.wait_spots.binary_search(&wait_spot).unwrap()
+    }
+
+    fn render(&self, state: &BurrowState) -> String {
+        let corridor_start = self.wait_spots[0];
+        let corridor_end = *self.wait_spots.last().unwrap();
+        let rooms_start = self.rooms[0].1;
+        let rooms_end = self.rooms.last().unwrap().1;
+
+        let mut lines = vec!["#".repeat(corridor_end + 2)];
+        lines.push(
+            (0..=corridor_end + 1)
+                .map(|x| match self.wait_spots.binary_search(&x) {
+                    Ok(i) => state.0[i] as char,
+                    Err(_) if (corridor_start..=corridor_end).contains(&x) => EMPTY as char,
+                    Err(_) => '#',
+                })
+                .collect(),
+        );
+        for depth in (0..self.room_depth).rev() {
+            lines.push(
+                (0..=corridor_end + 1)
+                    .map(
+                        |x| match self.rooms.iter().position(|(_, position)| *position == x) {
+                            Some(room) => state.0[self.room_offset(room) + depth] as char,
+                            None if depth == self.room_depth - 1
+                                || (rooms_start - 1..=rooms_end + 1).contains(&x) =>
+                            {
+                                '#'
+                            }
+                            None => ' ',
+                        },
+                    )
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string(),
+            );
+        }
+        lines.push(format!(
+            "{}{}",
+            " ".repeat(rooms_start - 1),
+            "#".repeat(rooms_end - rooms_start + 3)
+        ));
+        lines.join("\n")
+    }
+
+    // Every misplaced amphipod has to walk out to the corridor, over to its own room and at least
+    // one step into it, which never overestimates the remaining cost.
+    fn lower_bound(&self, state: &BurrowState, search: Search) -> usize {
+        if search == Dijkstra {
+            return 0;
+        }
+        let mut lower_bound = 0;
+        for (i, &x) in self.wait_spots.iter().enumerate() {
+            let amphipod = state.0[i];
+            if amphipod != EMPTY {
+                let destination = self.destination(amphipod);
+                lower_bound +=
+                    (x.abs_diff(self.rooms[destination].1) + 1) * self.move_costs[destination];
             }
-            match cheapest_solution {
-                Some((lowest_cost, _)) if lowest_cost < cost => continue,
-                _ => cheapest_solution = Some((cost, moves)),
+        }
+        for (room, &(owner, position)) in self.rooms.iter().enumerate() {
+            let occupants = self.occupants(state, room);
+            let num_settled = occupants
+                .iter()
+                .take_while(|&&occupant| occupant == owner as u8)
+                .count();
+            for (j, &amphipod) in occupants.iter().enumerate().skip(num_settled) {
+                let destination = self.destination(amphipod);
+                let horizontal_steps = if destination == room {
+                    2
+                } else {
+                    position.abs_diff(self.rooms[destination].1)
+                };
+                lower_bound +=
+                    (self.room_depth - j + horizontal_steps + 1) * self.move_costs[destination];
             }
         }
+        lower_bound
     }
-    cheapest_solution
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -214,97 +614,35 @@ enum PossibleMove {
     },
 }
 
-fn enumerate_possible_moves(
-    wait_spots: &HashMap<usize, Option<char>>,
-    rooms: &HashMap<char, (usize, Vec<char>)>,
-    room_depth: usize,
-) -> Vec<PossibleMove> {
-    let leftmost_wait_spot = *wait_spots.keys().min().unwrap();
-    let rightmost_wait_spot = *wait_spots.keys().max().unwrap();
-    let mut possible_moves = vec![];
-    for (&room, (position, occupants)) in rooms.iter() {
-        if !occupants.is_empty() && !occupants.iter().all(|occupant| *occupant == room) {
-            let amphipod = *occupants.last().unwrap();
-            let (destination_position, destination_occupation) = &rooms[&amphipod];
-            for x in (leftmost_wait_spot..*position).rev() {
-                match wait_spots.get(&x) {
-                    Some(Some(_)) => break,
-                    Some(None) => possible_moves.push(RoomToCorridor {
-                        amphipod,
-                        room,
-                        wait_spot: x,
-                        steps: position - x + room_depth + 1 - occupants.len(),
-                    }),
-                    None => (),
-                }
-                if x == *destination_position
-                    && (destination_occupation.is_empty()
-                        || destination_occupation == &vec![amphipod])
-                {
-                    possible_moves.push(RoomToRoom {
-                        amphipod,
-                        room,
-                        steps: position - x + room_depth + 1 - occupants.len() + room_depth
-                            - destination_occupation.len(),
-                    })
-                }
-            }
-            for x in *position..=rightmost_wait_spot {
-                match wait_spots.get(&x) {
-                    Some(Some(_)) => break,
-                    Some(None) => possible_moves.push(RoomToCorridor {
-                        amphipod,
-                        room,
-                        wait_spot: x,
-                        steps: x - position + room_depth + 1 - occupants.len(),
-                    }),
-                    None => (),
-                }
-                if x == *destination_position
-                    && (destination_occupation.is_empty()
-                        || destination_occupation == &vec![amphipod])
-                {
-                    possible_moves.push(RoomToRoom {
-                        amphipod,
-                        room,
-                        steps: x - position + room_depth + 1 - occupants.len() + room_depth
-                            - destination_occupation.len(),
-                    })
-                }
-            }
-        } else {
-            for x in (leftmost_wait_spot..*position).rev() {
-                match wait_spots.get(&x) {
-                    Some(Some(amphipod)) => {
-                        if *amphipod == room {
-                            possible_moves.push(CorridorToRoom {
-                                amphipod: *amphipod,
-                                wait_spot: x,
-                                steps: position - x + room_depth - occupants.len(),
-                            })
-                        }
-                        break;
-                    }
-                    _ => (),
-                }
-            }
-            for x in *position..=rightmost_wait_spot {
-                match wait_spots.get(&x) {
-                    Some(Some(amphipod)) => {
-                        if *amphipod == room {
-                            possible_moves.push(CorridorToRoom {
-                                amphipod: *amphipod,
-                                wait_spot: x,
-                                steps: x - position + room_depth - occupants.len(),
-                            })
-                        }
-                        break;
-                    }
-                    _ => (),
-                }
+impl fmt::Display for PossibleMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            RoomToRoom { amphipod, room, .. } => {
+                write!(
+                    f,
+                    "{} moves from room {} to room {}",
+                    amphipod, room, amphipod
+                )
             }
+            CorridorToRoom {
+                amphipod,
+                wait_spot,
+                ..
+            } => write!(
+                f,
+                "{} moves from the corridor at {} to room {}",
+                amphipod, wait_spot, amphipod
+            ),
+            RoomToCorridor {
+                amphipod,
+                room,
+                wait_spot,
+                ..
+            } => write!(
+                f,
+                "{} moves from room {} to the corridor at {}",
+                amphipod, room, wait_spot
+            ),
         }
     }
-    possible_moves.sort();
-    possible_moves
 }
@@ -0,0 +1,315 @@
+use std::fmt;
+use std::mem::swap;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Coordinate3D(pub [i32; 3]);
+
+impl Coordinate3D {
+    pub const ORIGIN: Self = Self([0, 0, 0]);
+
+    pub fn abs(&self) -> Self {
+        Self([self.0[0].abs(), self.0[1].abs(), self.0[2].abs()])
+    }
+
+    pub fn normalise(&mut self) {
+        let [mut x, mut y, mut z] = self.0;
+        if x > y {
+            swap(&mut x, &mut y)
+        }
+        if x > z {
+            swap(&mut x, &mut z)
+        }
+        if y > z {
+            swap(&mut y, &mut z)
+        }
+        *self = Self([x, y, z]);
+    }
+
+    pub fn manhattan_distance(&self, other: &Self) -> i32 {
+        (self - other).0.into_iter().fold(0, |a, b| a + b.abs())
+    }
+
+    pub fn squared_euclidean_distance(&self, other: &Self) -> i64 {
+        (self - other)
+            .0
+            .into_iter()
+            .fold(0, |a, b| a + b as i64 * b as i64)
+    }
+
+    pub fn euclidean_distance(&self, other: &Self) -> f64 {
+        (self.squared_euclidean_distance(other) as f64).sqrt()
+    }
+}
+
+macro_rules! impl_componentwise_operator {
+    ($operator:ident, $method:ident, $assign_operator:ident, $assign_method:ident, $op:tt) => {
+        impl $operator<Coordinate3D> for Coordinate3D {
+            type Output = Coordinate3D;
+
+            fn $method(self, rhs: Coordinate3D) -> Self::Output {
+                Coordinate3D([
+                    self.0[0] $op rhs.0[0],
+                    self.0[1] $op rhs.0[1],
+                    self.0[2] $op rhs.0[2],
+                ])
+            }
+        }
+
+        impl $operator<&Coordinate3D> for Coordinate3D {
+            type Output = Coordinate3D;
+
+            fn $method(self, rhs: &Coordinate3D) -> Self::Output {
+                self $op *rhs
+            }
+        }
+
+        impl $operator<Coordinate3D> for &Coordinate3D {
+            type Output = Coordinate3D;
+
+            fn $method(self, rhs: Coordinate3D) -> Self::Output {
+                *self $op rhs
+            }
+        }
+
+        impl $operator<&Coordinate3D> for &Coordinate3D {
+            type Output = Coordinate3D;
+
+            fn $method(self, rhs: &Coordinate3D) -> Self::Output {
+                *self $op *rhs
+            }
+        }
+
+        impl $assign_operator<Coordinate3D> for Coordinate3D {
+            fn $assign_method(&mut self, rhs: Coordinate3D) {
+                *self = *self $op rhs;
+            }
+        }
+    };
+}
+
+impl_componentwise_operator!(Add, add, AddAssign, add_assign, +);
+impl_componentwise_operator!(Sub, sub, SubAssign, sub_assign, -);
+
+impl Neg for Coordinate3D {
+    type Output = Coordinate3D;
+
+    fn neg(self) -> Self::Output {
+        Coordinate3D([-self.0[0], -self.0[1], -self.0[2]])
+    }
+}
+
+impl Neg for &Coordinate3D {
+    type Output = Coordinate3D;
+
+    fn neg(self) -> Self::Output {
+        -*self
+    }
+}
+
+impl Mul<i32> for Coordinate3D {
+    type Output = Coordinate3D;
+
+    fn mul(self, rhs: i32) -> Self::Output {
+        Coordinate3D([self.0[0] * rhs, self.0[1] * rhs, self.0[2] * rhs])
+    }
+}
+
+impl Mul<i32> for &Coordinate3D {
+    type Output = Coordinate3D;
+
+    fn mul(self, rhs: i32) -> Self::Output {
+        *self * rhs
+    }
+}
+
+impl MulAssign<i32> for Coordinate3D {
+    fn mul_assign(&mut self, rhs: i32) {
+        *self = *self * rhs;
+    }
+}
+
+// Each inner array is the image of one basis vector, i.e. a column of the matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RotationMatrix3D(pub [[i32; 3]; 3]);
+
+pub const ROTATIONS: [RotationMatrix3D; 24] = generate_rotations();
+
+const fn generate_rotations() -> [RotationMatrix3D; 24] {
+    let mut rotations = [RotationMatrix3D::IDENTITY; 24];
+    let signs = [1, -1];
+    let mut i = 0;
+    let mut x_sign_index = 0;
+    while x_sign_index < 2 {
+        let x_sign = signs[x_sign_index];
+        let mut x = 0;
+        while x < 3 {
+            let mut x_vector = [0, 0, 0];
+            x_vector[x] = x_sign;
+            let mut y_sign_index = 0;
+            while y_sign_index < 2 {
+                let y_sign = signs[y_sign_index];
+                let mut y_step = 1;
+                while y_step <= 2 {
+                    let y = (x + y_step) % 3;
+                    let mut y_vector = [0, 0, 0];
+                    y_vector[y] = y_sign;
+
+                    let z = 3 - x - y;
+                    let z_sign = signs[i % 2] * x_sign * y_sign;
+                    let mut z_vector = [0, 0, 0];
+                    z_vector[z] = z_sign;
+
+                    rotations[i] = RotationMatrix3D([x_vector, y_vector, z_vector]);
+
+                    i += 1;
+                    y_step += 1;
+                }
+                y_sign_index += 1;
+            }
+            x += 1;
+        }
+        x_sign_index += 1;
+    }
+    rotations
+}
+
+impl RotationMatrix3D {
+    pub const IDENTITY: Self = Self([[1, 0, 0], [0, 1, 0], [0, 0, 1]]);
+
+    pub fn compose(&self, other: &Self) -> Self {
+        *self * *other
+    }
+
+    pub fn inverse(&self) -> Self {
+        let mut inverse = Self([[0; 3]; 3]);
+        for i in 0..3 {
+            for j in 0..3 {
+                inverse.0[i][j] = self.0[j][i];
+            }
+        }
+        inverse
+    }
+}
+
+impl Mul<Coordinate3D> for RotationMatrix3D {
+    type Output = Coordinate3D;
+
+    fn mul(self, rhs: Coordinate3D) -> Self::Output {
+        let mut output = Coordinate3D([0, 0, 0]);
+        for i in 0..3 {
+            for j in 0..3 {
+                output.0[i] += self.0[j][i] * rhs.0[j];
+            }
+        }
+        output
+    }
+}
+
+impl Mul<&Coordinate3D> for RotationMatrix3D {
+    type Output = Coordinate3D;
+
+    fn mul(self, rhs: &Coordinate3D) -> Self::Output {
+        self * *rhs
+    }
+}
+
+impl Mul<RotationMatrix3D> for RotationMatrix3D {
+    type Output = RotationMatrix3D;
+
+    fn mul(self, rhs: RotationMatrix3D) -> Self::Output {
+        let mut output = Self([[0; 3]; 3]);
+        for (column, basis_image) in output.0.iter_mut().zip(rhs.0) {
+            *column = (self * Coordinate3D(basis_image)).0;
+        }
+        output
+    }
+}
+
+impl From<Coordinate3D> for [i64; 3] {
+    fn from(coordinate: Coordinate3D) -> Self {
+        coordinate.0.map(i64::from)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cuboid {
+    pub min: [i64; 3],
+    pub max: [i64; 3],
+}
+
+impl Cuboid {
+    pub const fn new(min: [i64; 3], max: [i64; 3]) -> Cuboid {
+        Cuboid { min, max }
+    }
+
+    pub fn around(centre: Coordinate3D, radius: i32) -> Cuboid {
+        let extent = Coordinate3D([radius, radius, radius]);
+        Cuboid::new((centre - extent).into(), (centre + extent).into())
+    }
+
+    pub fn contains(&self, point: &[i64; 3]) -> bool {
+        (0..3).all(|axis| (self.min[axis]..=self.max[axis]).contains(&point[axis]))
+    }
+
+    // An empty cuboid, with min > max on some axis, overlaps nothing.
+    pub fn overlaps(&self, cuboid: &Self) -> bool {
+        (0..3).all(|axis| {
+            i64::max(self.min[axis], cuboid.min[axis]) <= i64::min(self.max[axis], cuboid.max[axis])
+        })
+    }
+
+    pub fn volume(&self) -> u128 {
+        (0..3)
+            .map(|axis| (self.max[axis] as i128 + 1 - self.min[axis] as i128).max(0) as u128)
+            .product()
+    }
+
+    pub fn intersection(&self, cuboid: &Cuboid) -> Option<Cuboid> {
+        if self.overlaps(cuboid) {
+            let mut intersection = *self;
+            for axis in 0..3 {
+                intersection.min[axis] = i64::max(self.min[axis], cuboid.min[axis]);
+                intersection.max[axis] = i64::min(self.max[axis], cuboid.max[axis]);
+            }
+            Some(intersection)
+        } else {
+            None
+        }
+    }
+
+    pub fn difference(&self, cuboid: &Cuboid) -> Vec<Cuboid> {
+        let intersection = match self.intersection(cuboid) {
+            Some(intersection) => intersection,
+            None => return vec![*self],
+        };
+        let mut pieces = Vec::new();
+        let mut remainder = *self;
+        // Pieces are only cut off strictly beyond the intersection, so the +/- 1 never overflows.
+        for axis in 0..3 {
+            if remainder.min[axis] < intersection.min[axis] {
+                let mut piece = remainder;
+                piece.max[axis] = intersection.min[axis] - 1;
+                pieces.push(piece);
+                remainder.min[axis] = intersection.min[axis];
+            }
+            if remainder.max[axis] > intersection.max[axis] {
+                let mut piece = remainder;
+                piece.min[axis] = intersection.max[axis] + 1;
+                pieces.push(piece);
+                remainder.max[axis] = intersection.max[axis];
+            }
+        }
+        pieces
+    }
+}
+
+impl fmt::Display for Cuboid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "x={}..{},y={}..{},z={}..{}",
+            self.min[0], self.max[0], self.min[1], self.max[1], self.min[2], self.max[2]
+        )
+    }
+}